@@ -1,4 +1,4 @@
-use std::cmp;
+use std::collections::VecDeque;
 use std::num::Wrapping;
 use std::sync::{Arc, Mutex};
 use wayland_client::{protocol::wl_seat::WlSeat, Filter, Main};
@@ -11,8 +11,21 @@ use wayland_protocols::misc::zwp_input_method_v2::client::zwp_input_method_v2::{
     Event as InputMethodEvent, ZwpInputMethodV2,
 };
 
-use super::traits::{HintPurpose, IMVisibility, ReceiveSurroundingText};
-use super::SubmitError;
+use super::context::may_be_truncated;
+use super::history::{Edit, History};
+#[cfg(feature = "record")]
+use super::recording::{Record, Recorder};
+use super::sensitive::is_sensitive;
+use super::traits::{CursorPosition, HintPurpose, IMVisibility, ReceiveSurroundingText};
+use super::validation::ValidationState;
+use super::{
+    Divergence, EchoMode, EditCommand, Event, FilterMode, Preedit, Request, ReturnKeyAction,
+    ShiftState, SubmitError, TextContext, TextDelta, Transaction, ValidationMode, KEY_ENTER,
+};
+#[cfg(feature = "debug")]
+use std::borrow::Cow;
+#[cfg(feature = "record")]
+use std::{io, path::Path};
 
 // Mandatory conversion to apply filter to ZwpInputMethodV2
 mod event_enum {
@@ -23,14 +36,26 @@ mod event_enum {
     );
 }
 
+/// Result of comparing the surrounding text sent by the wayland server to the texts predicted after the commits
+enum Confirmation {
+    /// The changes were not caused by the last commit of the input method
+    Unrelated,
+    /// The changes match the predicted text
+    Echo,
+    /// The changes differ from the predicted text
+    Divergence(Divergence),
+}
+
 /// Stores the state of the input method
 #[derive(Clone, Debug)]
 struct IMProtocolState {
     surrounding_text: String,
     cursor: usize,
+    anchor: usize,
     content_purpose: ContentPurpose,
     content_hint: ContentHint,
     text_change_cause: ChangeCause,
+    preedit: Preedit,
     active: bool,
 }
 
@@ -39,15 +64,17 @@ impl Default for IMProtocolState {
         IMProtocolState {
             surrounding_text: String::new(),
             cursor: 0,
+            anchor: 0,
             content_hint: ContentHint::None,
             content_purpose: ContentPurpose::Normal,
             text_change_cause: ChangeCause::InputMethod,
+            preedit: Preedit::default(),
             active: false,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 /// Manages the pending state and the current state of the input method.
 ///
 /// It is called IMServiceArc and not IMService because the new() method
@@ -57,12 +84,43 @@ pub struct IMServiceArc<
     T: 'static + IMVisibility + HintPurpose,
     D: 'static + ReceiveSurroundingText,
 > {
-    im: Main<ZwpInputMethodV2>,
+    // None if the IMServiceArc is not connected to a wayland server
+    im: Option<Main<ZwpInputMethodV2>>,
     ui_connector: T,
     content_connector: D,
     pending: IMProtocolState,
     current: IMProtocolState,
+    // Number of 'done' events received, which has to be the serial of the next commit
     serial: Wrapping<u32>,
+    // Set if the wayland server sent 'unavailable'
+    unavailable: bool,
+    // Number of 'activate' events received, so users of the state can tell text inputs apart
+    activation: u64,
+    validation_mode: ValidationMode,
+    // Requests that were sent since the last commit
+    pending_transaction: Transaction,
+    echo_mode: EchoMode,
+    // Surrounding texts and cursors the wayland server is expected to send back after the commits it did not confirm
+    // yet, the oldest first
+    predicted: VecDeque<(String, usize)>,
+    // Set if the wayland server sent the surrounding text since the last 'done' event
+    surrounding_text_received: bool,
+    // Set from 'activate' until the content type is known, because the new text input could be sensitive
+    content_type_pending: bool,
+    // Set if the ui_connector should be advised about the shift state after each change
+    auto_capitalization: bool,
+    filter_mode: FilterMode,
+    // Edits of the input method that can be undone and redone
+    history: History,
+    #[cfg(feature = "record")]
+    recorder: Option<Recorder>,
+    // Records since the first event of the current cycle of events with their sensitivity when they were made.
+    // They are written with the 'done' event, when the content type of the text input is known
+    #[cfg(feature = "record")]
+    cycle_records: Vec<(Record, bool)>,
+    // Span of the current cycle of events and requests, which is opened by the first of them and closed by 'done'
+    #[cfg(feature = "tracing")]
+    cycle_span: Option<tracing::Span>,
 }
 
 impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D> {
@@ -77,14 +135,7 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
         let im = im_manager.get_input_method(seat);
 
         // Create IMServiceArc with default values
-        let im_service = IMServiceArc {
-            im,
-            ui_connector,
-            content_connector,
-            pending: IMProtocolState::default(),
-            current: IMProtocolState::default(),
-            serial: Wrapping(0u32),
-        };
+        let im_service = IMServiceArc::with_input_method(Some(im), ui_connector, content_connector);
 
         // Wrap IMServiceArc to allow mutability from multiple threads
         let im_service = Arc::new(Mutex::new(im_service));
@@ -99,34 +150,84 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
         im_service
     }
 
+    /// Creates a new IMServiceArc wrapped in Arc<Mutex<Self>>, that is not connected to a wayland server
+    ///
+    /// Requests are not sent anywhere and events have to be passed to handle_event()
+    pub fn new_detached(ui_connector: T, content_connector: D) -> Arc<Mutex<IMServiceArc<T, D>>> {
+        #[cfg(feature = "debug")]
+        info!("New detached IMService was created");
+        Arc::new(Mutex::new(IMServiceArc::with_input_method(
+            None,
+            ui_connector,
+            content_connector,
+        )))
+    }
+
+    /// This is a helper method for the constructors
+    ///
+    /// It creates a new IMServiceArc with default values
+    fn with_input_method(
+        im: Option<Main<ZwpInputMethodV2>>,
+        ui_connector: T,
+        content_connector: D,
+    ) -> IMServiceArc<T, D> {
+        IMServiceArc {
+            im,
+            ui_connector,
+            content_connector,
+            pending: IMProtocolState::default(),
+            current: IMProtocolState::default(),
+            serial: Wrapping(0u32),
+            unavailable: false,
+            activation: 0,
+            validation_mode: ValidationMode::default(),
+            pending_transaction: Transaction::new(),
+            echo_mode: EchoMode::default(),
+            predicted: VecDeque::new(),
+            surrounding_text_received: false,
+            content_type_pending: false,
+            auto_capitalization: false,
+            filter_mode: FilterMode::default(),
+            history: History::default(),
+            #[cfg(feature = "record")]
+            recorder: None,
+            #[cfg(feature = "record")]
+            cycle_records: Vec::new(),
+            #[cfg(feature = "tracing")]
+            cycle_span: None,
+        }
+    }
+
     /// Assigns a filter to the wayland event queue to allow IMServiceArc to handle events from ZwpInputMethodV2
     pub fn assign_filter(&self, im_service: Arc<Mutex<IMServiceArc<T, D>>>) {
         let filter = Filter::new(move |event, _, _| match event {
-            event_enum::Events::InputMethod { event, .. } => match event {
-                InputMethodEvent::Activate => im_service.lock().unwrap().handle_activate(),
-                InputMethodEvent::Deactivate => im_service.lock().unwrap().handle_deactivate(),
-                InputMethodEvent::SurroundingText {
-                    text,
-                    cursor,
-                    anchor,
-                } => im_service.lock().unwrap().handle_surrounding_text(
-                    text,
-                    cursor as usize,
-                    anchor as usize,
-                ),
-                InputMethodEvent::TextChangeCause { cause } => {
-                    im_service.lock().unwrap().handle_text_change_cause(cause)
-                }
-                InputMethodEvent::ContentType { hint, purpose } => im_service
-                    .lock()
-                    .unwrap()
-                    .handle_content_type(hint, purpose),
-                InputMethodEvent::Done => im_service.lock().unwrap().handle_done(),
-                InputMethodEvent::Unavailable => im_service.lock().unwrap().handle_unavailable(),
-                _ => (),
-            },
+            event_enum::Events::InputMethod { event, .. } => {
+                let event = match event {
+                    InputMethodEvent::Activate => Event::Activate,
+                    InputMethodEvent::Deactivate => Event::Deactivate,
+                    InputMethodEvent::SurroundingText {
+                        text,
+                        cursor,
+                        anchor,
+                    } => Event::SurroundingText {
+                        text,
+                        cursor: cursor as usize,
+                        anchor: anchor as usize,
+                    },
+                    InputMethodEvent::TextChangeCause { cause } => Event::TextChangeCause { cause },
+                    InputMethodEvent::ContentType { hint, purpose } => {
+                        Event::ContentType { hint, purpose }
+                    }
+                    InputMethodEvent::Done => Event::Done,
+                    InputMethodEvent::Unavailable => Event::Unavailable,
+                    _ => return,
+                };
+                im_service.lock().unwrap().process_event(event)
+            }
         });
-        self.im.assign(filter);
+        if let Some(im) = &self.im {
+            im.assign(filter);
+        }
         #[cfg(feature = "debug")]
         info!("The filter was assigned to Main<ZwpInputMethodV2>");
     }
@@ -135,21 +236,21 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     ///
     /// INPUTS: text -> Text that will be committed
     /// Wayland messages have a maximum length so the length of the text must not exceed 4000 bytes
+    ///
+    /// The text is inserted into the surrounding text with the next commit. If this method is called multiple times
+    /// before the commit, only the last text is inserted
     pub fn commit_string(&mut self, text: String) -> Result<(), SubmitError> {
         #[cfg(feature = "debug")]
-        info!("Commit string '{}'", text);
+        info!("Commit string '{}'", self.redact(&text));
         // Check if proxy is still alive. If the proxy was dead, the requests would fail silently
         match self.current.active {
             true => {
-                let cursor_position = self.pending.cursor;
-                // Append 'text' to the pending surrounding_text
-                self.pending
-                    .surrounding_text
-                    .insert_str(cursor_position, &text);
-                // Update the cursor
-                self.pending.cursor += text.len();
+                // Check or transform the text according to the content purpose
+                let text = self.filter_mode.apply(self.current.content_purpose, text)?;
+                // Remember the request to apply it to the surrounding_text with the next commit
                 // Send the request to the wayland-server
-                self.im.commit_string(text);
+                self.send(Request::CommitString { text: text.clone() })?;
+                self.pending_transaction.commit_string = Some(text);
                 Ok(())
             }
             false => Err(SubmitError::NotActive),
@@ -163,6 +264,8 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     /// before -> number of chars to delete from the surrounding_text going left from the cursor
     ///
     /// after  -> number of chars to delete from the surrounding_text going right from the cursor
+    ///
+    /// The text is deleted with the next commit, before any commit string is inserted
     pub fn delete_surrounding_text(
         &mut self,
         before: usize,
@@ -176,50 +279,412 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
         // Check if proxy is still alive. If the proxy was dead, the requests would fail silently
         match self.current.active {
             true => {
-                // Limit 'before' and 'after' if they exceed the maximum
-                let (before, after) = self.limit_before_after(before, after);
-                // Update self.pending.surrounging_text and self.pending.cursor
-                self.update_cursor_and_surrounding_text(before, after);
+                // Convert 'before' and 'after' to bytes and limit them if they exceed the maximum
+                let (before, after) = self.chars_to_bytes(before, after)?;
                 // Send the delete_surrounding_text request to the wayland-server
-                self.im.delete_surrounding_text(before as u32, after as u32);
+                self.send(Request::DeleteSurroundingText {
+                    before_length: before,
+                    after_length: after,
+                })?;
+                // Remember the request to apply it to the surrounding_text with the next commit
+                self.pending_transaction.delete = Some((before, after));
                 Ok(())
             }
             false => Err(SubmitError::NotActive),
         }
     }
 
+    /// Sends a 'set_preedit_string' request to the wayland server
+    ///
+    /// The preedit string is shown with the next commit. It is removed again with any later commit,
+    /// that does not set a preedit string
+    pub fn set_preedit_string(&mut self, preedit: Preedit) -> Result<(), SubmitError> {
+        #[cfg(feature = "debug")]
+        info!(
+            "Set preedit string '{}' with the cursor from {} to {}",
+            self.redact(&preedit.text),
+            preedit.cursor_begin,
+            preedit.cursor_end
+        );
+        // Check if proxy is still alive. If the proxy was dead, the requests would fail silently
+        match self.current.active {
+            true => {
+                preedit.validate()?;
+                // Send the request to the wayland-server
+                self.send(Request::SetPreeditString {
+                    text: preedit.text.clone(),
+                    cursor_begin: preedit.cursor_begin,
+                    cursor_end: preedit.cursor_end,
+                })?;
+                // Remember the request to apply it with the next commit
+                self.pending_transaction.preedit = Some(preedit);
+                Ok(())
+            }
+            false => Err(SubmitError::NotActive),
+        }
+    }
+
+    /// Sends all requests of the transaction followed by a 'commit' request to the wayland server
+    ///
+    /// The transaction is validated against the surrounding text before any request is sent.
+    /// Requests that were sent since the last commit are still applied with it, unless the transaction contains
+    /// a request of the same kind, which replaces them like on the wayland server
+    pub fn submit(&mut self, mut transaction: Transaction) -> Result<(), SubmitError> {
+        #[cfg(feature = "debug")]
+        info!(
+            "Submit transaction {}",
+            self.redact(&format!("{:?}", transaction))
+        );
+        // Check if proxy is still alive. If the proxy was dead, the requests would fail silently
+        if !self.current.active {
+            return Err(SubmitError::NotActive);
+        }
+        // Check or transform the text according to the content purpose
+        if let Some(text) = transaction.commit_string.take() {
+            let text = self.filter_mode.apply(self.current.content_purpose, text)?;
+            transaction.commit_string = Some(text);
+        }
+        let edit = self.send_transaction(transaction)?;
+        self.record_edit(edit);
+        Ok(())
+    }
+
+    /// Reverts the last edit of the input method
+    ///
+    /// The deleted text is inserted again and the inserted text is deleted, which leaves the cursor after the restored text.
+    /// Returns false if there is nothing to undo or if the text was changed since the edit, so it can not be reverted anymore
+    pub fn undo(&mut self) -> Result<bool, SubmitError> {
+        #[cfg(feature = "debug")]
+        info!("Undo the last edit");
+        if !self.current.active {
+            return Err(SubmitError::NotActive);
+        }
+        match self
+            .history
+            .undo_transaction(&self.pending.surrounding_text, self.pending.cursor)
+        {
+            Some(transaction) => {
+                self.send_transaction(transaction)?;
+                self.history.undone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Repeats the last edit that was reverted with undo()
+    ///
+    /// Returns false if there is nothing to redo or if the text was changed since the undo
+    pub fn redo(&mut self) -> Result<bool, SubmitError> {
+        #[cfg(feature = "debug")]
+        info!("Redo the last undone edit");
+        if !self.current.active {
+            return Err(SubmitError::NotActive);
+        }
+        match self
+            .history
+            .redo_transaction(&self.pending.surrounding_text, self.pending.cursor)
+        {
+            Some(transaction) => {
+                self.send_transaction(transaction)?;
+                self.history.redone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Executes the editing command on the surrounding text and commits the resulting requests
+    pub fn edit(&mut self, command: &EditCommand) -> Result<(), SubmitError> {
+        #[cfg(feature = "debug")]
+        info!(
+            "Execute edit command {}",
+            self.redact(&format!("{:?}", command))
+        );
+        if !self.current.active {
+            return Err(SubmitError::NotActive);
+        }
+        match command.to_transaction(&self.pending.surrounding_text, self.pending.cursor) {
+            Some(transaction) => self.submit(transaction),
+            None => Ok(()),
+        }
+    }
+
     /// Sends a 'commit' request to the wayland server
     ///
     /// This makes the pending changes permanent
     pub fn commit(&mut self) -> Result<(), SubmitError> {
+        let edit = self.commit_edit()?;
+        self.record_edit(edit);
+        Ok(())
+    }
+
+    /// This is a helper method for the commit, submit, undo and redo methods
+    ///
+    /// It sends the 'commit' request and returns the change of the surrounding text, without recording it in the history
+    fn commit_edit(&mut self) -> Result<Option<Edit>, SubmitError> {
         #[cfg(feature = "debug")]
         info!("Commit the changes");
         // Check if proxy is still alive. If the proxy was dead, the requests would fail silently
         match self.current.active {
             true => {
+                #[cfg(feature = "tracing")]
+                let _cycle_span = self.enter_cycle();
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!("commit", serial = self.serial.0).entered();
                 // Send request to wayland-server
-                self.im.commit(self.serial.0);
-                // Increase the serial
-                self.serial += Wrapping(1u32);
-                // Make pending changes permanent
-                self.pending_becomes_current();
-                Ok(())
+                self.send(Request::Commit {
+                    serial: self.serial.0,
+                })?;
+                // Apply the requests in the same order as the wayland server
+                let edit = self.apply_pending_transaction();
+                // The changes were caused by the input method
+                self.pending.text_change_cause = ChangeCause::InputMethod;
+                // Remember the result to compare it to the text the wayland server sends back
+                self.predicted
+                    .push_back((self.pending.surrounding_text.clone(), self.pending.cursor));
+                // Make pending changes permanent. They are the input method's own edit,
+                // so they are reported according to the EchoMode
+                self.pending_becomes_current(true);
+                Ok(edit)
             }
             false => Err(SubmitError::NotActive),
         }
     }
 
+    /// This is a helper method for the submit, undo and redo methods
+    ///
+    /// It validates the transaction, sends its requests and commits them together with the requests that were sent
+    /// since the last commit. Returns the change of the surrounding text
+    fn send_transaction(&mut self, transaction: Transaction) -> Result<Option<Edit>, SubmitError> {
+        // The wayland server still applies the requests that were sent before
+        let merged = self.pending_transaction.clone().merge(transaction.clone());
+        merged.validate(&self.pending.surrounding_text, self.pending.cursor)?;
+        let requests = transaction.requests();
+        // Check all requests first, so either all of them or none are sent
+        for request in &requests {
+            self.validate(request)?;
+        }
+        for request in requests {
+            self.send(request)?;
+        }
+        self.pending_transaction = merged;
+        self.commit_edit()
+    }
+
+    /// This is a helper method for the commit and submit methods
+    ///
+    /// Edits in text inputs for sensitive data are not recorded
+    fn record_edit(&mut self, edit: Option<Edit>) {
+        if let Some(edit) = edit {
+            if !self.is_sensitive() {
+                self.history.record(edit);
+            }
+        }
+    }
+
+    /// Sets how changes of the surrounding text are reported, that only mirror the input method's own commits
+    pub fn set_echo_mode(&mut self, echo_mode: EchoMode) {
+        #[cfg(feature = "debug")]
+        info!("The echo mode was set to {:?}", echo_mode);
+        self.echo_mode = echo_mode;
+    }
+
+    /// Enables or disables advising the ui_connector about the shift state after each change
+    pub fn set_auto_capitalization(&mut self, enabled: bool) {
+        #[cfg(feature = "debug")]
+        info!("Auto capitalization was set to {}", enabled);
+        self.auto_capitalization = enabled;
+    }
+
+    /// Sets how committed text is checked against the content purpose
+    pub fn set_filter_mode(&mut self, filter_mode: FilterMode) {
+        #[cfg(feature = "debug")]
+        info!("The filter mode was set to {:?}", filter_mode);
+        self.filter_mode = filter_mode;
+    }
+
+    /// Passes the candidates of a Pipeline to the ui_connector
+    pub(crate) fn show_candidates(&self, candidates: &[String]) {
+        self.ui_connector.show_candidates(candidates);
+    }
+
+    /// Returns what the return key should do in the current text input
+    pub fn return_key_action(&self) -> ReturnKeyAction {
+        ReturnKeyAction::resolve(self.current.content_hint, self.current.content_purpose)
+    }
+
+    /// Commits a line break or calls 'send_key' with the keycode of the enter key, depending on the current text input
+    pub fn press_return<F: FnOnce(u32)>(
+        &mut self,
+        send_key: F,
+    ) -> Result<ReturnKeyAction, SubmitError> {
+        #[cfg(feature = "debug")]
+        info!("Return was pressed");
+        if !self.current.active {
+            return Err(SubmitError::NotActive);
+        }
+        let action = self.return_key_action();
+        match action.sends_key() {
+            true => send_key(KEY_ENTER),
+            false => self.submit(Transaction::new().commit_string(String::from("\n")))?,
+        }
+        Ok(action)
+    }
+
+    /// Returns if the current text input contains sensitive data like passwords
+    pub fn is_sensitive(&self) -> bool {
+        is_sensitive(self.current.content_hint, self.current.content_purpose)
+    }
+
+    /// Sets how requests are checked against the rules of the protocol before they are sent
+    pub fn set_validation_mode(&mut self, validation_mode: ValidationMode) {
+        #[cfg(feature = "debug")]
+        info!("The validation mode was set to {:?}", validation_mode);
+        self.validation_mode = validation_mode;
+    }
+
+    /// Returns the content hint and purpose of the current text input
+    pub fn content_type(&self) -> (ContentHint, ContentPurpose) {
+        (self.current.content_hint, self.current.content_purpose)
+    }
+
     /// Returns if the input method is currently active
     pub fn is_active(&self) -> bool {
         self.current.active
     }
 
+    /// Returns the preedit string that was shown with the last commit
+    pub fn get_preedit(&self) -> Preedit {
+        self.current.preedit.clone()
+    }
+
+    /// Returns the words and sentence information around the cursor of the current surrounding text
+    pub fn text_context(&self, previous_word_count: usize) -> TextContext {
+        TextContext::new(
+            &self.current.surrounding_text,
+            self.current.cursor,
+            previous_word_count,
+        )
+    }
+
     /// Returns a tuple of the current strings left and right of the cursor
+    ///
+    /// If the cursor is not within the text or splits a char, both strings are empty
     pub fn get_surrounding_text(&self) -> (String, String) {
         #[cfg(feature = "debug")]
         info!("Requested surrounding text");
-        let (left_str, right_str) = self.pending.surrounding_text.split_at(self.current.cursor);
-        (left_str.to_string(), right_str.to_string())
+        let text = &self.current.surrounding_text;
+        match (
+            text.get(..self.current.cursor),
+            text.get(self.current.cursor..),
+        ) {
+            (Some(left_str), Some(right_str)) => (left_str.to_string(), right_str.to_string()),
+            _ => (String::new(), String::new()),
+        }
+    }
+
+    /// Returns the number of 'activate' events received
+    ///
+    /// It changes whenever a text input is activated, even if the input method was deactivated in between
+    pub(crate) fn activation(&self) -> u64 {
+        self.activation
+    }
+
+    /// Handles an event like it was sent from the wayland server
+    ///
+    /// This method should only be called from the client to replay recorded events or for tests.
+    /// The event is ignored if the IMServiceArc is connected to a wayland server, so the state can not diverge from it
+    pub fn handle_event(&mut self, event: Event) {
+        if self.im.is_some() {
+            #[cfg(feature = "debug")]
+            info!(
+                "The event was ignored, because the IMServiceArc is connected to a wayland server"
+            );
+            return;
+        }
+        self.process_event(event);
+    }
+
+    /// This is a helper method for the handle_event method and the filter of the wayland events
+    ///
+    /// It records the event and updates the pending state
+    fn process_event(&mut self, event: Event) {
+        #[cfg(feature = "tracing")]
+        let _span = self.enter_cycle();
+        #[cfg(feature = "record")]
+        self.record(Record::Event(event.clone()));
+        match event {
+            Event::Activate => self.handle_activate(),
+            Event::Deactivate => self.handle_deactivate(),
+            Event::SurroundingText {
+                text,
+                cursor,
+                anchor,
+            } => self.handle_surrounding_text(text, cursor, anchor),
+            Event::TextChangeCause { cause } => self.handle_text_change_cause(cause),
+            Event::ContentType { hint, purpose } => self.handle_content_type(hint, purpose),
+            Event::Done => self.handle_done(),
+            Event::Unavailable => self.handle_unavailable(),
+        }
+    }
+
+    /// Enters the span of the current cycle of events and requests and opens it if this is the first of them
+    ///
+    /// The state the cycle resulted in is recorded in the span when the 'done' event is handled
+    #[cfg(feature = "tracing")]
+    fn enter_cycle(&mut self) -> tracing::span::EnteredSpan {
+        let serial = self.serial.0;
+        self.cycle_span
+            .get_or_insert_with(|| {
+                tracing::debug_span!(
+                    "cycle",
+                    serial = serial,
+                    active = tracing::field::Empty,
+                    purpose = tracing::field::Empty,
+                    cause = tracing::field::Empty
+                )
+            })
+            .clone()
+            .entered()
+    }
+
+    /// Applies a recorded request like it was sent by the client
+    ///
+    /// The request is not checked or filtered again, because that already happened when it was recorded
+    #[cfg(feature = "record")]
+    pub fn replay_request(&mut self, request: Request) {
+        if let Request::Commit { .. } = request {
+            // The commit sends the request itself
+            let _ = self.commit();
+            return;
+        }
+        if self.send(request.clone()).is_err() {
+            return;
+        }
+        match request {
+            Request::CommitString { text } => {
+                self.pending_transaction.commit_string = Some(text);
+            }
+            Request::DeleteSurroundingText {
+                before_length,
+                after_length,
+            } => {
+                self.pending_transaction.delete = Some((before_length, after_length));
+            }
+            Request::SetPreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => {
+                self.pending_transaction.preedit = Some(Preedit {
+                    text,
+                    cursor_begin,
+                    cursor_end,
+                });
+            }
+            Request::Commit { .. } => (),
+        }
     }
 
     /// Handles the 'activate' event sent from the wayland server
@@ -228,10 +693,17 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     fn handle_activate(&mut self) {
         #[cfg(feature = "debug")]
         info!("handle_activate() was called");
+        #[cfg(feature = "tracing")]
+        tracing::debug!(event = "activate");
         self.pending = IMProtocolState {
             active: true,
             ..IMProtocolState::default()
         };
+        // The content type is reset, but the wayland server can send the surrounding text before the real one
+        self.content_type_pending = true;
+        self.activation += 1;
+        // Edits in the previous text input can not be confirmed anymore
+        self.predicted.clear();
     }
 
     /// Handles the 'deactivate' event sent from the wayland server
@@ -240,21 +712,33 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     fn handle_deactivate(&mut self) {
         #[cfg(feature = "debug")]
         info!("handle_deactivate() was called");
+        #[cfg(feature = "tracing")]
+        tracing::debug!(event = "deactivate");
         self.pending.active = false;
     }
 
     /// Handles the 'surrounding_text' event sent from the wayland server
     ///
     /// This method should never be called from the client
-    /// The 'anchor' parameter is currently not being used
     fn handle_surrounding_text(&mut self, text: String, cursor: usize, anchor: usize) {
         #[cfg(feature = "debug")]
         info!(
-            "handle_surrounding_text(text: '{}', cursor: {}) was called",
-            text, cursor
+            "handle_surrounding_text(text: '{}', cursor: {}, anchor: {}) was called",
+            self.redact(&text),
+            cursor,
+            anchor
+        );
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            event = "surrounding_text",
+            bytes = text.len(),
+            cursor = cursor,
+            anchor = anchor
         );
         self.pending.surrounding_text = text;
         self.pending.cursor = cursor;
+        self.pending.anchor = anchor;
+        self.surrounding_text_received = true;
     }
 
     /// Handles the 'text_change_cause' event sent from the wayland server
@@ -263,6 +747,8 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     fn handle_text_change_cause(&mut self, cause: ChangeCause) {
         #[cfg(feature = "debug")]
         info!("handle_text_change_cause() was called");
+        #[cfg(feature = "tracing")]
+        tracing::debug!(event = "text_change_cause", cause = ?cause);
         self.pending.text_change_cause = cause;
     }
 
@@ -272,8 +758,15 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     fn handle_content_type(&mut self, hint: ContentHint, purpose: ContentPurpose) {
         #[cfg(feature = "debug")]
         info!("handle_content_type() was called");
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            event = "content_type",
+            hint = hint.bits(),
+            purpose = ?purpose
+        );
         self.pending.content_hint = hint;
         self.pending.content_purpose = purpose;
+        self.content_type_pending = false;
     }
 
     /// Handles the 'done' event sent from the wayland server
@@ -282,7 +775,49 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     fn handle_done(&mut self) {
         #[cfg(feature = "debug")]
         info!("handle_done() was called");
-        self.pending_becomes_current();
+        #[cfg(feature = "tracing")]
+        {
+            tracing::debug!(event = "done");
+            // The cycle ends with this event, the span stays entered until it was handled
+            if let Some(span) = self.cycle_span.take() {
+                span.record("active", self.pending.active);
+                span.record(
+                    "purpose",
+                    tracing::field::debug(&self.pending.content_purpose),
+                );
+                span.record(
+                    "cause",
+                    tracing::field::debug(&self.pending.text_change_cause),
+                );
+            }
+        }
+        // The serial of the next commit is the number of 'done' events received
+        self.serial += Wrapping(1u32);
+        // The edits can not be undone anymore if the application changed the text or another text input was activated
+        let changed_by_app =
+            self.surrounding_text_received && self.pending.text_change_cause == ChangeCause::Other;
+        if changed_by_app || self.pending.active != self.current.active {
+            self.history.clear();
+        }
+        let echo = match self.compare_with_prediction() {
+            Confirmation::Echo => true,
+            Confirmation::Divergence(divergence) => {
+                #[cfg(feature = "debug")]
+                info!(
+                    "The surrounding text diverged from the predicted text '{}'",
+                    self.redact(&divergence.predicted_text)
+                );
+                // The pending state already contains the confirmed text, so the mirror is resynchronized
+                // when it becomes the current state
+                self.content_connector.text_diverged(divergence);
+                false
+            }
+            Confirmation::Unrelated => false,
+        };
+        self.surrounding_text_received = false;
+        self.pending_becomes_current(echo);
+        // Without a 'content_type' event, the text input keeps the default content type
+        self.content_type_pending = false;
     }
 
     /// Handles the 'unavailable' event sent from the wayland server
@@ -291,34 +826,125 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     fn handle_unavailable(&mut self) {
         #[cfg(feature = "debug")]
         info!("handle_unavailable() was called");
-        self.im.destroy();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(event = "unavailable");
+        if let Some(im) = &self.im {
+            im.destroy();
+        }
+        self.unavailable = true;
         self.current.active = false;
         self.ui_connector.deactivate_im();
     }
 
+    /// This is a helper method for the handle_done method
+    ///
+    /// Compares the surrounding text the wayland server sent to the texts predicted after the commits of the input method,
+    /// that were not confirmed yet. The matching prediction and all older ones are consumed by the comparison.
+    /// If later commits are still unconfirmed, the pending text is set to the latest prediction again,
+    /// because the wayland server will apply them to the confirmed text.
+    ///
+    /// The text diverged if it matches none of the predictions even though the input method caused the change,
+    /// for example because the application rejected or reformatted the committed text.
+    /// Truncated texts are not compared, because they only contain a window of the predicted text
+    fn compare_with_prediction(&mut self) -> Confirmation {
+        if !self.surrounding_text_received || self.predicted.is_empty() {
+            return Confirmation::Unrelated;
+        }
+        if self.pending.text_change_cause != ChangeCause::InputMethod {
+            // The application changed the text, so the commits can not be confirmed anymore
+            self.predicted.clear();
+            return Confirmation::Unrelated;
+        }
+        if may_be_truncated(&self.pending.surrounding_text) {
+            self.predicted.pop_front();
+            return Confirmation::Unrelated;
+        }
+        let matching = self.predicted.iter().position(|(text, cursor)| {
+            *text == self.pending.surrounding_text && *cursor == self.pending.cursor
+        });
+        match matching {
+            Some(index) => {
+                self.predicted.drain(..=index);
+                if let Some((text, cursor)) = self.predicted.back() {
+                    self.pending.surrounding_text = text.clone();
+                    self.pending.cursor = *cursor;
+                    self.pending.anchor = *cursor;
+                }
+                Confirmation::Echo
+            }
+            None => {
+                let (predicted_text, predicted_cursor) =
+                    self.predicted.pop_front().unwrap_or_default();
+                // The confirmed text replaces all predictions
+                self.predicted.clear();
+                Confirmation::Divergence(Divergence {
+                    predicted_text,
+                    predicted_cursor,
+                    confirmed_text: self.pending.surrounding_text.clone(),
+                    confirmed_cursor: self.pending.cursor,
+                })
+            }
+        }
+    }
+
     /// This is a helper method
     ///
-    /// It moves the values of self.pending to self.current and notifies the connector, to show or hide the keyboard.
+    /// It moves the values of self.pending to self.current and notifies the connectors, to show or hide the keyboard
+    /// and about changes of the surrounding text or the cursor.
     ///
-    /// It should only be called if the wayland-server or the client committed the pending changes
-    fn pending_becomes_current(&mut self) {
+    /// It should only be called if the wayland-server or the client committed the pending changes.
+    /// If 'echo' is true, the changes were made by the input method itself and are reported according to the EchoMode
+    fn pending_becomes_current(&mut self, echo: bool) {
         #[cfg(feature = "debug")]
         info!("The pending protocol state became the current state");
         let active_changed = self.current.active ^ self.pending.active;
+        let sensitive_changed =
+            is_sensitive(self.current.content_hint, self.current.content_purpose)
+                != is_sensitive(self.pending.content_hint, self.pending.content_purpose);
         let text_changed = self.current.surrounding_text != self.pending.surrounding_text;
+        let cursor_changed = self.current.cursor != self.pending.cursor
+            || self.current.anchor != self.pending.anchor;
+        let notify_content = !(echo && self.echo_mode == EchoMode::Suppress);
+        #[cfg(feature = "debug")]
+        if !notify_content {
+            info!("The changes only mirror the last commit and are not reported");
+        }
 
         // Make pending changes permanent
-        self.current = self.pending.clone();
+        let previous = std::mem::replace(&mut self.current, self.pending.clone());
 
-        if text_changed {
+        if text_changed && notify_content {
             #[cfg(feature = "debug")]
             info!(
                 "The surrounding text changed to '{}'",
-                self.current.surrounding_text
+                self.redact(&self.current.surrounding_text)
+            );
+            self.content_connector.text_delta(TextDelta {
+                echo: echo && self.echo_mode == EchoMode::Tag,
+                ..TextDelta::between(
+                    &previous.surrounding_text,
+                    &self.current.surrounding_text,
+                    self.current.cursor,
+                )
+            });
+            if self.content_connector.wants_full_text() {
+                let (left_str, right_str) = self.get_surrounding_text();
+                self.content_connector.text_changed(left_str, right_str);
+            }
+        }
+
+        if cursor_changed && notify_content {
+            #[cfg(feature = "debug")]
+            info!(
+                "The cursor moved to {} and the anchor to {}",
+                self.current.cursor, self.current.anchor
+            );
+            let position = CursorPosition::new(
+                &self.current.surrounding_text,
+                self.current.cursor,
+                self.current.anchor,
             );
-            let (left_str, right_str) = self.current.surrounding_text.split_at(self.current.cursor);
-            let (left_str, right_str) = (left_str.to_string(), right_str.to_string());
-            self.content_connector.text_changed(left_str, right_str);
+            self.content_connector.cursor_changed(position);
         }
 
         // Notify connector about changes
@@ -331,6 +957,196 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
                 self.ui_connector.deactivate_im();
             };
         }
+
+        if (active_changed || sensitive_changed) && self.current.active {
+            #[cfg(feature = "debug")]
+            info!("The text input is sensitive: {}", self.is_sensitive());
+            self.ui_connector.set_sensitive(self.is_sensitive());
+        }
+
+        // Advise the shift state for the next letter
+        if self.auto_capitalization && self.current.active {
+            let shift_state = ShiftState::advise(
+                self.current.content_hint,
+                self.current.content_purpose,
+                &self.text_context(0),
+            );
+            #[cfg(feature = "debug")]
+            info!("The advised shift state is {:?}", shift_state);
+            self.ui_connector.set_shift_state(shift_state);
+        }
+    }
+
+    /// This is a helper method to send requests to the wayland server
+    ///
+    /// The request is validated first, if a ValidationMode is set.
+    /// If the IMServiceArc is not connected to a wayland server, the request is only recorded
+    fn send(&mut self, request: Request) -> Result<(), SubmitError> {
+        #[cfg(feature = "tracing")]
+        let _span = self.enter_cycle();
+        self.validate(&request)?;
+        #[cfg(feature = "tracing")]
+        match &request {
+            Request::CommitString { text } => {
+                tracing::debug!(request = "commit_string", bytes = text.len())
+            }
+            Request::DeleteSurroundingText {
+                before_length,
+                after_length,
+            } => tracing::debug!(
+                request = "delete_surrounding_text",
+                before_bytes = before_length,
+                after_bytes = after_length
+            ),
+            Request::SetPreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => tracing::debug!(
+                request = "set_preedit_string",
+                bytes = text.len(),
+                cursor_begin = cursor_begin,
+                cursor_end = cursor_end
+            ),
+            Request::Commit { serial } => tracing::debug!(request = "commit", serial = serial),
+        }
+        #[cfg(feature = "record")]
+        self.record(Record::Request(request.clone()));
+        let im = match &self.im {
+            Some(im) => im,
+            None => return Ok(()),
+        };
+        match request {
+            Request::CommitString { text } => im.commit_string(text),
+            Request::DeleteSurroundingText {
+                before_length,
+                after_length,
+            } => im.delete_surrounding_text(before_length as u32, after_length as u32),
+            Request::SetPreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => im.set_preedit_string(text, cursor_begin, cursor_end),
+            Request::Commit { serial } => im.commit(serial),
+        }
+        Ok(())
+    }
+
+    /// This is a helper method for the send method
+    ///
+    /// It checks the request against the rules of the protocol and reports violations according to the ValidationMode
+    fn validate(&self, request: &Request) -> Result<(), SubmitError> {
+        if self.validation_mode == ValidationMode::Off {
+            return Ok(());
+        }
+        let state = ValidationState {
+            surrounding_text: &self.pending.surrounding_text,
+            cursor: self.pending.cursor,
+            done_count: self.serial.0,
+            unavailable: self.unavailable,
+        };
+        match state.check(request) {
+            Ok(()) => Ok(()),
+            Err(violation) => {
+                #[cfg(feature = "debug")]
+                info!("The request violates the protocol: {:?}", violation);
+                self.validation_mode.report(violation)
+            }
+        }
+    }
+
+    /// Starts writing every event and request to the file at 'path'
+    ///
+    /// An existing file is overwritten. Texts of sensitive text inputs are masked
+    #[cfg(feature = "record")]
+    pub fn start_recording(&mut self, path: &Path) -> io::Result<()> {
+        #[cfg(feature = "debug")]
+        info!("Start recording to {:?}", path);
+        self.recorder = Some(Recorder::create(path)?);
+        Ok(())
+    }
+
+    /// Stops writing events and requests to the file
+    ///
+    /// Records of an unfinished cycle of events are written masked, because the content type could still change
+    #[cfg(feature = "record")]
+    pub fn stop_recording(&mut self) {
+        #[cfg(feature = "debug")]
+        info!("Stop recording");
+        self.write_cycle_records(true);
+        self.recorder = None;
+        self.cycle_records.clear();
+    }
+
+    /// This is a helper method for recording
+    ///
+    /// Events and the requests sent between them are held back until the 'done' event. The wayland server can send
+    /// the surrounding text of a newly activated text input before its content type, so only then it is known
+    /// which texts have to be masked. Requests outside of a cycle of events are written right away
+    #[cfg(feature = "record")]
+    fn record(&mut self, record: Record) {
+        if self.recorder.is_none() {
+            return;
+        }
+        let sensitive = self.is_sensitive()
+            || is_sensitive(self.pending.content_hint, self.pending.content_purpose);
+        let done = record == Record::Event(Event::Done);
+        match &record {
+            Record::Request(_) if self.cycle_records.is_empty() => {
+                self.cycle_records.push((record, sensitive));
+                self.write_cycle_records(false);
+            }
+            _ => {
+                self.cycle_records.push((record, sensitive));
+                if done {
+                    // The pending content type is final with the 'done' event
+                    self.write_cycle_records(sensitive);
+                }
+            }
+        }
+    }
+
+    /// This is a helper method for recording
+    ///
+    /// It writes the held back records to the file and masks them if 'sensitive' is set or if they were sensitive
+    /// when they were made. If writing fails, the recording is stopped
+    #[cfg(feature = "record")]
+    fn write_cycle_records(&mut self, sensitive: bool) {
+        let records = std::mem::take(&mut self.cycle_records);
+        let recorder = match &mut self.recorder {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        for (record, sensitive_when_made) in records {
+            if let Err(_err) = recorder.record(record, sensitive || sensitive_when_made) {
+                #[cfg(feature = "debug")]
+                info!("Recording failed and was stopped: {}", _err);
+                self.recorder = None;
+                return;
+            }
+        }
+    }
+
+    /// This is a helper method for logging
+    ///
+    /// Returns a placeholder instead of the text if the current or the pending text input could contain sensitive data
+    #[cfg(feature = "debug")]
+    fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.may_be_sensitive() {
+            true => Cow::Owned(format!("<{} bytes redacted>", text.len())),
+            false => Cow::Borrowed(text),
+        }
+    }
+
+    /// This is a helper method for logging
+    ///
+    /// Returns true if the current or the pending text input contain sensitive data or if a text input was activated
+    /// and its content type is not known yet
+    #[cfg(feature = "debug")]
+    fn may_be_sensitive(&self) -> bool {
+        self.is_sensitive()
+            || is_sensitive(self.pending.content_hint, self.pending.content_purpose)
+            || self.content_type_pending
     }
 
     /// This is a helper method for the delete_surrounding_text method
@@ -344,12 +1160,12 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     ///
     /// OUTPUTS:
     ///
-    /// before (limited) -> number of chars to delete from the surrounding_text going left from the cursor  (limited)
+    /// before (limited) -> number of bytes to delete from the surrounding_text going left from the cursor  (limited)
     ///
-    /// after  (limited) -> number of chars to delete from the surrounding_text going right from the cursor (limited)
+    /// after  (limited) -> number of bytes to delete from the surrounding_text going right from the cursor (limited)
     ///
     ///
-    /// The wayland server ignores 'delete_surrounding_text' requests under the following conditions:
+    /// The wayland server expects the lengths in bytes and ignores 'delete_surrounding_text' requests under the following conditions:
     ///
     /// A: cursor_position < before
     ///
@@ -357,56 +1173,72 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMServiceArc<T, D
     ///
     /// B: cursor_position + after > surrounding_text.len()
     ///
-    /// This method limits the values of before and after to those maximums so no requests will be ignored.
-    fn limit_before_after(&self, before: usize, after: usize) -> (usize, usize) {
-        let cursor_position = self.pending.cursor;
-        let before = cmp::min(cursor_position, before);
-        let after = cmp::min(self.pending.surrounding_text.len() - cursor_position, after);
-        (before, after)
+    /// This method converts the number of chars to bytes and limits them to those maximums so no requests will be ignored.
+    /// If the cursor sent by the wayland server is not within the surrounding text or splits a char,
+    /// SubmitError::InvalidDeleteRange is returned
+    fn chars_to_bytes(&self, before: usize, after: usize) -> Result<(usize, usize), SubmitError> {
+        let text = &self.pending.surrounding_text;
+        let (string_left_of_cursor, string_right_of_cursor) = match (
+            text.get(..self.pending.cursor),
+            text.get(self.pending.cursor..),
+        ) {
+            (Some(left_str), Some(right_str)) => (left_str, right_str),
+            _ => return Err(SubmitError::InvalidDeleteRange),
+        };
+        let before = string_left_of_cursor
+            .chars()
+            .rev()
+            .take(before)
+            .map(char::len_utf8)
+            .sum();
+        let after = string_right_of_cursor
+            .chars()
+            .take(after)
+            .map(char::len_utf8)
+            .sum();
+        Ok((before, after))
     }
 
-    /// This is a helper method for the delete_surrounding_text method
-    ///
-    /// INPUTS:
+    /// This is a helper method for the commit method
     ///
-    /// before -> number of chars to delete from the surrounding_text going left from the cursor
-    ///
-    /// after  -> number of chars to delete from the surrounding_text going right from the cursor
-    ///
-    /// This method removes the amount of chars requested from self.pending.surrounding_text. This deletion not only affects the surrounding_text
-    /// but also the cursor position.
-    fn update_cursor_and_surrounding_text(&mut self, before: usize, after: usize) {
-        let cursor_position = self.pending.cursor as usize;
-
-        // Get str left and right of the cursor to remove the requested amount of chars from each of them
-        let (string_left_of_cursor, old_string_right_of_cursor) =
-            self.pending.surrounding_text.split_at(cursor_position);
-
-        // Make a String from the reference
-        let mut string_left_of_cursor = String::from(string_left_of_cursor);
-        let mut string_right_of_cursor = String::from("");
-
-        // Pop of as many chars as requested with the before parameter
-        // The result is the string on the left side of the cursor for the new surrounding_text
-        for _ in 0..before {
-            string_left_of_cursor.pop();
-        }
-
-        // Skip as many chars as requested with the after parameter and then add all remaining chars
-        // The result is the string on the right side of the cursor for the new surrounding_text
-        for character in old_string_right_of_cursor.chars().skip(after as usize) {
-            string_right_of_cursor.push(character);
+    /// It applies the requests sent since the last commit to self.pending in the order the wayland server applies them:
+    /// first the surrounding text is deleted, then the commit string is inserted and at last the preedit string is shown.
+    /// This deletion and insertion not only affects the surrounding_text but also the cursor position.
+    /// Returns the change of the surrounding text, if there was one
+    fn apply_pending_transaction(&mut self) -> Option<Edit> {
+        let transaction = std::mem::take(&mut self.pending_transaction);
+        let mut edit = None;
+        if transaction.delete.is_some() || transaction.commit_string.is_some() {
+            // The wayland server could have changed the surrounding text since the requests were sent
+            match transaction.validate(&self.pending.surrounding_text, self.pending.cursor) {
+                Ok(()) => {
+                    let (before, after) = transaction.delete.unwrap_or((0, 0));
+                    let position = self.pending.cursor - before;
+                    edit = Some(Edit {
+                        position,
+                        removed: self.pending.surrounding_text
+                            [position..self.pending.cursor + after]
+                            .to_string(),
+                        inserted: transaction.commit_string.clone().unwrap_or_default(),
+                    })
+                    .filter(|edit| !edit.removed.is_empty() || !edit.inserted.is_empty());
+                    let cursor =
+                        transaction.apply(&mut self.pending.surrounding_text, self.pending.cursor);
+                    // A commit never leaves a selection behind
+                    self.pending.cursor = cursor;
+                    self.pending.anchor = cursor;
+                }
+                Err(_err) => {
+                    #[cfg(feature = "debug")]
+                    info!(
+                        "The requests could not be applied to the surrounding text: {:?}",
+                        _err
+                    );
+                }
+            }
         }
-
-        // Get the new position of the cursor
-        let new_cursor_position = string_left_of_cursor.len();
-
-        // Join the string of the left and the right sides to make the new surrounding_text
-        let mut new_surrounding_text = string_left_of_cursor;
-        new_surrounding_text.push_str(&string_right_of_cursor);
-
-        // Apply the new values of the cursor and the new surrounding_text to self
-        self.pending.surrounding_text = new_surrounding_text;
-        self.pending.cursor = new_cursor_position;
+        // The preedit string is removed if it was not set again
+        self.pending.preedit = transaction.preedit.unwrap_or_default();
+        edit
     }
 }
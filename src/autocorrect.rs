@@ -0,0 +1,237 @@
+use super::capitalization::{capitalized, is_capitalized, is_uppercase};
+use super::hunspell::Dictionary;
+use super::{EngineAction, EngineContext, Input, InputEngine, TextContext};
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ContentPurpose;
+
+/// Word and its correction, which can be reverted
+#[derive(Clone, Debug)]
+struct Substitution {
+    original: String,
+    // Correction followed by the typed separator
+    replacement: String,
+}
+
+/// Engine that corrects misspelled words when they are ended by a separator like a space or a period
+///
+/// The word left of the cursor is looked up in a Hunspell dictionary. If it is not found, it is replaced with the
+/// best correction and the separator in a single commit. Corrections are tried in this order:
+/// the replacements of the REP table, the capitalized word, swapped neighbouring chars, a neighbouring key of the
+/// KEY table, a missing char, an additional char and a wrong char. Chars are tried in the order of the TRY setting.
+///
+/// A backspace right after a correction restores the original word without the separator.
+/// The original word is accepted afterwards, so it is not corrected again.
+///
+/// The engine should be the last of a pipeline, because engines after it do not see the corrected word in the
+/// surrounding text. It is disabled in text inputs for sensitive data or with a purpose other than
+/// ContentPurpose::Normal or ContentPurpose::Alpha
+#[derive(Clone, Debug, Default)]
+pub struct AutocorrectEngine {
+    dictionary: Dictionary,
+    // Words that were reverted by the user
+    accepted: HashSet<String>,
+    // Last correction, which is reverted by the next backspace
+    substitution: Option<Substitution>,
+}
+
+impl AutocorrectEngine {
+    /// Creates a new AutocorrectEngine without any words, so it does not correct anything
+    pub fn new() -> Self {
+        AutocorrectEngine::default()
+    }
+
+    /// Creates a new AutocorrectEngine with the Hunspell dictionary of the .aff file at 'aff_path' and the .dic file
+    /// at 'dic_path', like en_US.aff and en_US.dic
+    pub fn from_hunspell<A: AsRef<Path>, D: AsRef<Path>>(
+        aff_path: A,
+        dic_path: D,
+    ) -> io::Result<Self> {
+        let mut engine = AutocorrectEngine::new();
+        engine.load_hunspell(aff_path, dic_path)?;
+        Ok(engine)
+    }
+
+    /// Adds the words of the Hunspell dictionary with the .aff file at 'aff_path' and the .dic file at 'dic_path'
+    ///
+    /// The prefixes and suffixes are applied to the words and the TRY, KEY and REP settings are used for the
+    /// corrections. The files are encoded in UTF-8 or ISO8859-1. Compounds are not supported
+    pub fn load_hunspell<A: AsRef<Path>, D: AsRef<Path>>(
+        &mut self,
+        aff_path: A,
+        dic_path: D,
+    ) -> io::Result<()> {
+        self.dictionary.load(aff_path, dic_path)
+    }
+
+    /// Adds a word that is correct and can be a correction
+    pub fn add_word(&mut self, word: String) {
+        self.dictionary.add_word(word);
+    }
+
+    /// Returns true if the word does not need a correction
+    pub fn is_correct(&self, word: &str) -> bool {
+        self.accepted.contains(word) || self.dictionary.contains(word)
+    }
+
+    /// Returns the best correction for the word or None if it is correct or no correction is found
+    ///
+    /// The correction keeps the capitalization of the word
+    pub fn correct(&self, word: &str) -> Option<String> {
+        if self.is_correct(word) {
+            return None;
+        }
+        let lowercase = word.to_lowercase();
+        let correction = self.candidates(&lowercase).into_iter().find(|candidate| {
+            candidate
+                .split(' ')
+                .all(|part| self.dictionary.can_suggest(part))
+        })?;
+        match (is_uppercase(word), is_capitalized(word)) {
+            (true, _) => Some(correction.to_uppercase()),
+            (false, true) => Some(capitalized(&correction)),
+            (false, false) => Some(correction),
+        }
+    }
+
+    /// Returns the possible corrections of the lowercase word, the best first
+    fn candidates(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        // Replaces 'removed' chars at the index by the inserted chars
+        let edit = |index: usize, removed: usize, inserted: &[char]| -> String {
+            chars[..index]
+                .iter()
+                .chain(inserted)
+                .chain(&chars[index + removed..])
+                .collect()
+        };
+        let try_chars = &self.dictionary.try_chars;
+        let mut candidates: Vec<String> = self
+            .dictionary
+            .replacements
+            .iter()
+            .filter(|(from, _)| !from.is_empty() && word.contains(from.as_str()))
+            .map(|(from, to)| word.replacen(from.as_str(), to, 1))
+            .collect();
+        candidates.push(capitalized(word));
+        for index in 1..chars.len() {
+            candidates.push(edit(index - 1, 2, &[chars[index], chars[index - 1]]));
+        }
+        for (index, c) in chars.iter().enumerate() {
+            for neighbour in self.neighbours(*c) {
+                candidates.push(edit(index, 1, &[neighbour]));
+            }
+        }
+        for index in 0..chars.len() {
+            candidates.push(edit(index, 1, &[]));
+        }
+        for index in 0..=chars.len() {
+            candidates.extend(try_chars.iter().map(|c| edit(index, 0, &[*c])));
+        }
+        for index in 0..chars.len() {
+            candidates.extend(try_chars.iter().map(|c| edit(index, 1, &[*c])));
+        }
+        candidates.retain(|candidate| candidate != word);
+        candidates
+    }
+
+    /// Returns the keys left and right of the key of the char in the KEY table
+    fn neighbours(&self, c: char) -> Vec<char> {
+        let mut neighbours = Vec::new();
+        for row in &self.dictionary.keyboard {
+            for (index, _) in row.iter().enumerate().filter(|(_, key)| **key == c) {
+                neighbours.extend(index.checked_sub(1).and_then(|index| row.get(index)));
+                neighbours.extend(row.get(index + 1));
+            }
+        }
+        neighbours
+    }
+
+    /// Returns the actions to replace the word left of the cursor with its correction followed by the typed text
+    fn autocorrect(&mut self, typed: String, context: &EngineContext) -> Option<Vec<EngineAction>> {
+        // The word is only complete if the cursor is at its end
+        if context.text_after_cursor.starts_with(char::is_alphanumeric) {
+            return None;
+        }
+        let before = &context.text_before_cursor;
+        let text_context = TextContext::new(before, before.len(), 0);
+        let word = match text_context.current_word {
+            Some(word) if word.complete && word.range.end == before.len() => word.text,
+            _ => return None,
+        };
+        // Numbers and single letters are not corrected
+        if word.chars().count() < 2 || word.chars().any(char::is_numeric) {
+            return None;
+        }
+        let correction = self.correct(&word)?;
+        let replacement = correction + &typed;
+        let actions = vec![
+            EngineAction::Delete {
+                before: word.chars().count(),
+                after: 0,
+            },
+            EngineAction::Commit(replacement.clone()),
+        ];
+        self.substitution = Some(Substitution {
+            original: word,
+            replacement,
+        });
+        Some(actions)
+    }
+}
+
+impl InputEngine for AutocorrectEngine {
+    fn process(&mut self, input: Input, context: &EngineContext) -> Vec<EngineAction> {
+        let enabled = !context.sensitive
+            && matches!(
+                context.content_purpose,
+                ContentPurpose::Normal | ContentPurpose::Alpha
+            );
+        let substitution = self.substitution.take();
+        if !enabled {
+            return vec![EngineAction::Forward(input)];
+        }
+        let typed = match &input {
+            Input::Backspace => {
+                // Revert the last correction if it is still left of the cursor
+                return match substitution {
+                    Some(substitution)
+                        if context
+                            .text_before_cursor
+                            .ends_with(&substitution.replacement) =>
+                    {
+                        let actions = vec![
+                            EngineAction::Delete {
+                                before: substitution.replacement.chars().count(),
+                                after: 0,
+                            },
+                            EngineAction::Commit(substitution.original.clone()),
+                        ];
+                        self.accepted.insert(substitution.original);
+                        actions
+                    }
+                    _ => vec![EngineAction::Forward(input)],
+                };
+            }
+            Input::Char(c) => c.to_string(),
+            Input::Text(text) => text.clone(),
+            _ => return vec![EngineAction::Forward(input)],
+        };
+        match typed.chars().next() {
+            Some(c) if is_separator(c) => self
+                .autocorrect(typed, context)
+                .unwrap_or_else(|| vec![EngineAction::Forward(input)]),
+            _ => vec![EngineAction::Forward(input)],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.substitution = None;
+    }
+}
+
+/// Returns true if the char ends a word. Apostrophes and hyphens can be part of a word
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || (c.is_ascii_punctuation() && !"'-_".contains(c))
+}
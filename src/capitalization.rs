@@ -0,0 +1,173 @@
+use super::TextContext;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
+
+/// Advice whether the next letter should be capitalized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShiftState {
+    /// The next letter should be lowercase
+    Lower,
+    /// The next letter should be uppercase and the following ones lowercase
+    Upper,
+    /// All letters should be uppercase
+    CapsLock,
+}
+
+impl ShiftState {
+    /// Advises the shift state for the next letter based on the content type and the text around the cursor
+    ///
+    /// The hints Uppercase and Lowercase apply to all letters, Titlecase and the purpose Name capitalize the start of
+    /// each word and AutoCapitalization capitalizes the start of each sentence. Purposes like Email, Url or Password
+    /// are never capitalized automatically
+    pub fn advise(hint: ContentHint, purpose: ContentPurpose, context: &TextContext) -> ShiftState {
+        let at_word_start = context.current_word_prefix().is_empty();
+        match purpose {
+            ContentPurpose::Email
+            | ContentPurpose::Url
+            | ContentPurpose::Password
+            | ContentPurpose::Pin
+            | ContentPurpose::Terminal => return ShiftState::Lower,
+            ContentPurpose::Name if at_word_start => return ShiftState::Upper,
+            _ => (),
+        }
+        let capitalize_word = hint.contains(ContentHint::Titlecase);
+        let capitalize_sentence =
+            hint.contains(ContentHint::AutoCapitalization) && context.at_sentence_start;
+        if hint.contains(ContentHint::Lowercase) {
+            ShiftState::Lower
+        } else if hint.contains(ContentHint::Uppercase) {
+            ShiftState::CapsLock
+        } else if at_word_start && (capitalize_word || capitalize_sentence) {
+            ShiftState::Upper
+        } else {
+            ShiftState::Lower
+        }
+    }
+}
+
+/// Returns true if the first char of the word is uppercase and all others are lowercase
+pub(crate) fn is_capitalized(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(char::is_uppercase) && !chars.any(char::is_uppercase)
+}
+
+/// Returns true if the word has more than one char and all letters are uppercase
+pub(crate) fn is_uppercase(word: &str) -> bool {
+    word.chars().count() > 1 && !word.chars().any(char::is_lowercase)
+}
+
+/// Returns the word with an uppercase first letter
+pub(crate) fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advise(hint: ContentHint, purpose: ContentPurpose, text: &str) -> ShiftState {
+        ShiftState::advise(hint, purpose, &TextContext::new(text, text.len(), 0))
+    }
+
+    #[test]
+    fn advises_by_position() {
+        let hint = ContentHint::AutoCapitalization;
+        let cases = [
+            ("", ShiftState::Upper),
+            ("Hello. ", ShiftState::Upper),
+            ("Hello! How? ", ShiftState::Upper),
+            ("Hello ", ShiftState::Lower),
+            ("Hel", ShiftState::Lower),
+            ("Hello. Wor", ShiftState::Lower),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(
+                advise(hint, ContentPurpose::Normal, text),
+                expected,
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn advises_by_hint() {
+        let cases = [
+            (ContentHint::None, "", ShiftState::Lower),
+            (ContentHint::None, "Hello. ", ShiftState::Lower),
+            (ContentHint::Uppercase, "", ShiftState::CapsLock),
+            (ContentHint::Uppercase, "HEL", ShiftState::CapsLock),
+            (ContentHint::Titlecase, "Hello ", ShiftState::Upper),
+            (ContentHint::Titlecase, "Hel", ShiftState::Lower),
+            (ContentHint::Lowercase, "", ShiftState::Lower),
+            (
+                ContentHint::Lowercase | ContentHint::AutoCapitalization,
+                "Hello. ",
+                ShiftState::Lower,
+            ),
+            (
+                ContentHint::Uppercase | ContentHint::AutoCapitalization,
+                "Hello ",
+                ShiftState::CapsLock,
+            ),
+            (
+                ContentHint::AutoCapitalization,
+                "Hello. ",
+                ShiftState::Upper,
+            ),
+            (ContentHint::AutoCapitalization, "Hello ", ShiftState::Lower),
+        ];
+        for (hint, text, expected) in cases {
+            assert_eq!(
+                advise(hint, ContentPurpose::Normal, text),
+                expected,
+                "{:?} {:?}",
+                hint,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn advises_by_purpose() {
+        let hint = ContentHint::AutoCapitalization | ContentHint::Titlecase;
+        let cases = [
+            (
+                ContentPurpose::Name,
+                ContentHint::None,
+                "Ada ",
+                ShiftState::Upper,
+            ),
+            (
+                ContentPurpose::Name,
+                ContentHint::None,
+                "Ad",
+                ShiftState::Lower,
+            ),
+            (ContentPurpose::Email, hint, "", ShiftState::Lower),
+            (ContentPurpose::Url, hint, "", ShiftState::Lower),
+            (ContentPurpose::Password, hint, "", ShiftState::Lower),
+            (ContentPurpose::Pin, hint, "", ShiftState::Lower),
+            (
+                ContentPurpose::Terminal,
+                ContentHint::Uppercase,
+                "",
+                ShiftState::Lower,
+            ),
+        ];
+        for (purpose, hint, text, expected) in cases {
+            assert_eq!(
+                advise(hint, purpose, text),
+                expected,
+                "{:?} {:?}",
+                purpose,
+                text
+            );
+        }
+    }
+}
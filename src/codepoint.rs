@@ -0,0 +1,100 @@
+use super::{EngineAction, EngineContext, Input, InputEngine};
+
+/// Maximum number of hex digits of a Unicode code point
+const MAX_DIGITS: usize = 6;
+
+/// Engine to type chars by the hex digits of their Unicode code point, like Ctrl+Shift+U in GTK
+///
+/// The mode is started with the trigger key. The typed hex digits are shown as the preedit string after a 'u'.
+/// A space or Input::Confirm commits the char if the digits are a valid code point. Input::Cancel
+/// or deleting all digits ends the mode without committing anything. Other chars are ignored while the mode is active.
+///
+/// Resetting the engine cancels the mode without committing the typed digits. The Pipeline resets it when the input
+/// method is deactivated or another text input is activated, so a half-typed code point never ends up in another text
+#[derive(Clone, Debug)]
+pub struct CodePointEngine {
+    trigger: u32,
+    // Typed hex digits or None if the mode is not active
+    digits: Option<String>,
+}
+
+impl CodePointEngine {
+    /// Creates a new CodePointEngine that is started with Input::Key(trigger)
+    pub fn new(trigger: u32) -> Self {
+        CodePointEngine {
+            trigger,
+            digits: None,
+        }
+    }
+
+    /// Returns true if the engine is waiting for hex digits
+    pub fn is_active(&self) -> bool {
+        self.digits.is_some()
+    }
+
+    /// Returns the char of the typed code point or None if it is not a valid Unicode scalar value
+    fn char(digits: &str) -> Option<char> {
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .filter(|code_point| *code_point != 0)
+            .and_then(char::from_u32)
+    }
+
+    /// Returns the preedit string for the typed digits
+    fn preedit(digits: &str) -> EngineAction {
+        EngineAction::Preedit(format!("u{}", digits))
+    }
+}
+
+impl InputEngine for CodePointEngine {
+    fn process(&mut self, input: Input, _context: &EngineContext) -> Vec<EngineAction> {
+        let digits = match &mut self.digits {
+            Some(digits) => digits,
+            None if input == Input::Key(self.trigger) => {
+                self.digits = Some(String::new());
+                return vec![CodePointEngine::preedit("")];
+            }
+            None => return vec![EngineAction::Forward(input)],
+        };
+        match input {
+            Input::Char(c) if c.is_ascii_hexdigit() => {
+                if digits.len() < MAX_DIGITS {
+                    digits.push(c.to_ascii_lowercase());
+                }
+                vec![CodePointEngine::preedit(digits)]
+            }
+            Input::Char(' ') | Input::Confirm => match CodePointEngine::char(digits) {
+                Some(c) => {
+                    self.digits = None;
+                    vec![
+                        EngineAction::Preedit(String::new()),
+                        EngineAction::Commit(c.to_string()),
+                    ]
+                }
+                // The digits can be corrected
+                None if !digits.is_empty() => Vec::new(),
+                None => {
+                    self.digits = None;
+                    vec![EngineAction::Preedit(String::new())]
+                }
+            },
+            Input::Backspace if !digits.is_empty() => {
+                digits.pop();
+                vec![CodePointEngine::preedit(digits)]
+            }
+            Input::Backspace | Input::Cancel => {
+                self.digits = None;
+                vec![EngineAction::Preedit(String::new())]
+            }
+            // The trigger and chars that are not hex digits are ignored
+            Input::Char(_) | Input::Select(_) => Vec::new(),
+            Input::Key(key) if key == self.trigger => Vec::new(),
+            input => vec![EngineAction::Forward(input)],
+        }
+    }
+
+    fn reset(&mut self) {
+        // The digits are discarded, the preedit string is removed by the wayland server on deactivation
+        self.digits = None;
+    }
+}
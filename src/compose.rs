@@ -0,0 +1,626 @@
+use super::{EngineAction, EngineContext, Input, InputEngine};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Linux input event code of the compose key, which starts a compose sequence
+pub const KEY_COMPOSE: u32 = input_event_codes::KEY_COMPOSE!();
+
+/// Key of a compose sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    /// The compose key, called Multi_key in Compose files
+    Compose,
+    /// A char. Dead keys are represented by the combining char of their accent
+    Char(char),
+}
+
+/// Engine that composes chars from sequences of keys like the compose key followed by an apostrophe and an 'e'
+///
+/// The sequences are loaded from files in the format of X11 Compose files. The compose key is sent as
+/// Input::Key(KEY_COMPOSE) and dead keys are sent as Input::Char with the combining char of their accent,
+/// for example '\u{301}' for dead_acute. Sequences that contain keysyms without a known char are skipped.
+///
+/// While a sequence is typed, it is shown as the preedit string. If the keys do not match any sequence,
+/// the typed chars are committed unchanged
+#[derive(Clone, Debug, Default)]
+pub struct ComposeEngine {
+    sequences: HashMap<Vec<Key>, String>,
+    // All incomplete beginnings of the sequences
+    prefixes: HashSet<Vec<Key>>,
+    // Keys of the sequence typed so far
+    typed: Vec<Key>,
+}
+
+impl ComposeEngine {
+    /// Creates a new ComposeEngine without any sequences
+    pub fn new() -> Self {
+        ComposeEngine::default()
+    }
+
+    /// Creates a new ComposeEngine with the sequences of the Compose file at 'path'
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut engine = ComposeEngine::new();
+        engine.load_file(path)?;
+        Ok(engine)
+    }
+
+    /// Adds the sequences of the Compose file at 'path'. Sequences that were already loaded are replaced
+    ///
+    /// Included files are loaded as well. In the path of an include '%H' is replaced with the home directory.
+    /// Includes of the system files with '%L' or '%S' are skipped, because their location depends on the system
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.load_file_once(path.as_ref(), &mut HashSet::new())
+    }
+
+    /// Loads the file unless it is in 'visited', so files that include each other are loaded only once
+    fn load_file_once(&mut self, path: &Path, visited: &mut HashSet<PathBuf>) -> io::Result<()> {
+        if !visited.insert(fs::canonicalize(path)?) {
+            return Ok(());
+        }
+        let content = fs::read_to_string(path)?;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            } else if let Some(include) = line.strip_prefix("include") {
+                if let Some(path) = parse_include(include) {
+                    self.load_file_once(Path::new(&path), visited)?;
+                }
+            } else if let Some((keys, result)) = parse_sequence(line) {
+                self.add_sequence(keys, result);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a sequence. It is skipped if a loaded sequence starts with it or if it starts with a loaded sequence,
+    /// because every typed key has to either complete or continue a sequence
+    fn add_sequence(&mut self, keys: Vec<Key>, result: String) {
+        if keys.is_empty() || self.prefixes.contains(&keys) {
+            return;
+        }
+        for length in 1..keys.len() {
+            if self.sequences.contains_key(&keys[..length]) {
+                return;
+            }
+        }
+        for length in 1..keys.len() {
+            self.prefixes.insert(keys[..length].to_vec());
+        }
+        self.sequences.insert(keys, result);
+    }
+
+    /// Returns the number of sequences that were loaded
+    pub fn len(&self) -> usize {
+        self.sequences.len()
+    }
+
+    /// Returns true if no sequences were loaded
+    pub fn is_empty(&self) -> bool {
+        self.sequences.is_empty()
+    }
+
+    /// Returns the typed chars of the sequence and discards it
+    fn take_raw(&mut self) -> String {
+        std::mem::take(&mut self.typed)
+            .into_iter()
+            .filter_map(|key| match key {
+                Key::Char(c) => Some(c),
+                Key::Compose => None,
+            })
+            .collect()
+    }
+
+    /// Returns the preedit string for the typed keys
+    fn preedit(&self) -> String {
+        self.typed
+            .iter()
+            .map(|key| match key {
+                Key::Compose => "⎄".to_string(),
+                // Combining chars are shown on a dotted circle
+                Key::Char(c) if is_combining(*c) => format!("◌{}", c),
+                Key::Char(c) => c.to_string(),
+            })
+            .collect()
+    }
+
+    /// Adds the key to the typed sequence and returns the resulting actions
+    fn type_key(&mut self, key: Key) -> Vec<EngineAction> {
+        self.typed.push(key);
+        if let Some(result) = self.sequences.get(&self.typed) {
+            let result = result.clone();
+            self.typed.clear();
+            return vec![
+                EngineAction::Preedit(String::new()),
+                EngineAction::Commit(result),
+            ];
+        }
+        match self.prefixes.contains(&self.typed) {
+            true => vec![EngineAction::Preedit(self.preedit())],
+            false => vec![
+                EngineAction::Preedit(String::new()),
+                EngineAction::Commit(self.take_raw()),
+            ],
+        }
+    }
+}
+
+impl InputEngine for ComposeEngine {
+    fn process(&mut self, input: Input, _context: &EngineContext) -> Vec<EngineAction> {
+        let key = match input {
+            Input::Key(KEY_COMPOSE) => Some(Key::Compose),
+            Input::Char(c) => Some(Key::Char(c)),
+            _ => None,
+        };
+        match (key, self.typed.is_empty()) {
+            // Only keys that start a sequence are composed
+            (Some(key), true)
+                if self.prefixes.contains(&vec![key])
+                    || self.sequences.contains_key(&[key][..]) =>
+            {
+                self.type_key(key)
+            }
+            (Some(key), false) => self.type_key(key),
+            (_, true) => vec![EngineAction::Forward(input)],
+            (None, false) => match input {
+                Input::Backspace => {
+                    self.typed.pop();
+                    vec![EngineAction::Preedit(self.preedit())]
+                }
+                Input::Cancel => {
+                    self.typed.clear();
+                    vec![EngineAction::Preedit(String::new())]
+                }
+                // Any other input ends the sequence
+                input => vec![
+                    EngineAction::Preedit(String::new()),
+                    EngineAction::Commit(self.take_raw()),
+                    EngineAction::Forward(input),
+                ],
+            },
+        }
+    }
+
+    fn reset(&mut self) {
+        self.typed.clear();
+    }
+}
+
+/// Parses a line like '<Multi_key> <apostrophe> <e> : "é" eacute # comment'
+///
+/// Returns None for comments, empty lines and lines that can not be parsed
+fn parse_sequence(line: &str) -> Option<(Vec<Key>, String)> {
+    let (left, right) = line.split_once(':')?;
+    let mut keys = Vec::new();
+    for key in left.split('<').skip(1) {
+        let name = key.split('>').next()?.trim();
+        keys.push(match name {
+            "Multi_key" => Key::Compose,
+            name => Key::Char(keysym_to_char(name)?),
+        });
+    }
+    let right = right.trim_start();
+    let result = match right.strip_prefix('"') {
+        Some(string) => parse_string(string)?,
+        None => {
+            let keysym = right
+                .split(|c: char| c.is_whitespace() || c == '#')
+                .next()?;
+            keysym_to_char(keysym)?.to_string()
+        }
+    };
+    Some((keys, result))
+}
+
+/// Parses the rest of a quoted string after the opening quote
+fn parse_string(string: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = string.chars();
+    loop {
+        match chars.next()? {
+            '"' => return Some(result),
+            '\\' => match chars.next()? {
+                'n' => result.push('\n'),
+                'r' => result.push('\r'),
+                'x' | 'X' => {
+                    let hex: String = chars.clone().take_while(char::is_ascii_hexdigit).collect();
+                    result.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    chars.nth(hex.len().checked_sub(1)?);
+                }
+                c if c.is_digit(8) => {
+                    let octal: String = std::iter::once(c)
+                        .chain(chars.clone().take_while(|c| c.is_digit(8)).take(2))
+                        .collect();
+                    result.push(char::from_u32(u32::from_str_radix(&octal, 8).ok()?)?);
+                    if octal.len() > 1 {
+                        chars.nth(octal.len() - 2);
+                    }
+                }
+                c => result.push(c),
+            },
+            c => result.push(c),
+        }
+    }
+}
+
+/// Parses the path of an include line and replaces the substitutions
+fn parse_include(include: &str) -> Option<String> {
+    let path = include.trim().strip_prefix('"')?.split('"').next()?;
+    let mut result = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('%', Some('H')) => {
+                result.push_str(&std::env::var("HOME").ok()?);
+                chars.next();
+            }
+            ('%', Some('%')) => {
+                result.push('%');
+                chars.next();
+            }
+            // The locations of the system files are unknown
+            ('%', _) => return None,
+            (c, _) => result.push(c),
+        }
+    }
+    Some(result)
+}
+
+/// Returns true if the char is a combining diacritical mark like the accents of dead keys
+fn is_combining(c: char) -> bool {
+    ('\u{300}'..='\u{36F}').contains(&c)
+}
+
+/// Returns the char of a keysym name
+///
+/// Single chars, Unicode keysyms like 'U00E9', the names of the ASCII and Latin-1 chars and dead keys are known
+fn keysym_to_char(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(c);
+    }
+    if let Some(hex) = name.strip_prefix('U') {
+        if hex.len() >= 4 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return char::from_u32(u32::from_str_radix(hex, 16).ok()?);
+        }
+    }
+    if let Some(accent) = name.strip_prefix("dead_") {
+        return DEAD_KEYS
+            .iter()
+            .find(|(name, _)| *name == accent)
+            .map(|(_, c)| *c);
+    }
+    KEYSYMS
+        .iter()
+        .position(|keysym| *keysym == name)
+        .and_then(|index| match index < ASCII_KEYSYMS {
+            // The keysyms of the ASCII chars start at the space
+            true => char::from_u32(0x20 + index as u32),
+            // The keysyms of the Latin-1 chars start at the no-break space
+            false => char::from_u32(0xA0 + (index - ASCII_KEYSYMS) as u32),
+        })
+}
+
+/// Accents of the dead keys with their combining chars
+const DEAD_KEYS: [(&str, char); 24] = [
+    ("grave", '\u{300}'),
+    ("acute", '\u{301}'),
+    ("circumflex", '\u{302}'),
+    ("tilde", '\u{303}'),
+    ("perispomeni", '\u{342}'),
+    ("macron", '\u{304}'),
+    ("breve", '\u{306}'),
+    ("abovedot", '\u{307}'),
+    ("diaeresis", '\u{308}'),
+    ("hook", '\u{309}'),
+    ("abovering", '\u{30A}'),
+    ("doubleacute", '\u{30B}'),
+    ("caron", '\u{30C}'),
+    ("abovecomma", '\u{313}'),
+    ("psili", '\u{313}'),
+    ("abovereversedcomma", '\u{314}'),
+    ("dasia", '\u{314}'),
+    ("horn", '\u{31B}'),
+    ("belowdot", '\u{323}'),
+    ("belowcomma", '\u{326}'),
+    ("cedilla", '\u{327}'),
+    ("ogonek", '\u{328}'),
+    ("stroke", '\u{338}'),
+    ("iota", '\u{345}'),
+];
+
+/// Number of keysyms for the printable ASCII chars at the start of KEYSYMS
+const ASCII_KEYSYMS: usize = 95;
+
+/// Names of the keysyms of the printable ASCII chars from 0x20 and of the Latin-1 chars from 0xA0 in the order of their chars
+const KEYSYMS: [&str; 191] = [
+    "space",
+    "exclam",
+    "quotedbl",
+    "numbersign",
+    "dollar",
+    "percent",
+    "ampersand",
+    "apostrophe",
+    "parenleft",
+    "parenright",
+    "asterisk",
+    "plus",
+    "comma",
+    "minus",
+    "period",
+    "slash",
+    "0",
+    "1",
+    "2",
+    "3",
+    "4",
+    "5",
+    "6",
+    "7",
+    "8",
+    "9",
+    "colon",
+    "semicolon",
+    "less",
+    "equal",
+    "greater",
+    "question",
+    "at",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "bracketleft",
+    "backslash",
+    "bracketright",
+    "asciicircum",
+    "underscore",
+    "grave",
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "braceleft",
+    "bar",
+    "braceright",
+    "asciitilde",
+    "nobreakspace",
+    "exclamdown",
+    "cent",
+    "sterling",
+    "currency",
+    "yen",
+    "brokenbar",
+    "section",
+    "diaeresis",
+    "copyright",
+    "ordfeminine",
+    "guillemotleft",
+    "notsign",
+    "hyphen",
+    "registered",
+    "macron",
+    "degree",
+    "plusminus",
+    "twosuperior",
+    "threesuperior",
+    "acute",
+    "mu",
+    "paragraph",
+    "periodcentered",
+    "cedilla",
+    "onesuperior",
+    "masculine",
+    "guillemotright",
+    "onequarter",
+    "onehalf",
+    "threequarters",
+    "questiondown",
+    "Agrave",
+    "Aacute",
+    "Acircumflex",
+    "Atilde",
+    "Adiaeresis",
+    "Aring",
+    "AE",
+    "Ccedilla",
+    "Egrave",
+    "Eacute",
+    "Ecircumflex",
+    "Ediaeresis",
+    "Igrave",
+    "Iacute",
+    "Icircumflex",
+    "Idiaeresis",
+    "ETH",
+    "Ntilde",
+    "Ograve",
+    "Oacute",
+    "Ocircumflex",
+    "Otilde",
+    "Odiaeresis",
+    "multiply",
+    "Oslash",
+    "Ugrave",
+    "Uacute",
+    "Ucircumflex",
+    "Udiaeresis",
+    "Yacute",
+    "THORN",
+    "ssharp",
+    "agrave",
+    "aacute",
+    "acircumflex",
+    "atilde",
+    "adiaeresis",
+    "aring",
+    "ae",
+    "ccedilla",
+    "egrave",
+    "eacute",
+    "ecircumflex",
+    "ediaeresis",
+    "igrave",
+    "iacute",
+    "icircumflex",
+    "idiaeresis",
+    "eth",
+    "ntilde",
+    "ograve",
+    "oacute",
+    "ocircumflex",
+    "otilde",
+    "odiaeresis",
+    "division",
+    "oslash",
+    "ugrave",
+    "uacute",
+    "ucircumflex",
+    "udiaeresis",
+    "yacute",
+    "thorn",
+    "ydiaeresis",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences_are_parsed() {
+        assert_eq!(
+            parse_sequence("<Multi_key> <apostrophe> <e> : \"é\" eacute # comment"),
+            Some((
+                vec![Key::Compose, Key::Char('\''), Key::Char('e')],
+                "é".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_sequence("<dead_acute> <U0065> : eacute"),
+            Some((vec![Key::Char('\u{301}'), Key::Char('e')], "é".to_string()))
+        );
+        // Keysyms without a known char are skipped
+        assert_eq!(parse_sequence("<Multi_key> <KP_Add> : \"+\""), None);
+        assert_eq!(parse_sequence("# <Multi_key> <e> \"é\""), None);
+    }
+
+    #[test]
+    fn escapes_are_parsed() {
+        assert_eq!(parse_string("a\\\"b\\\\c\""), Some("a\"b\\c".to_string()));
+        assert_eq!(parse_string("\\n\\r\""), Some("\n\r".to_string()));
+        assert_eq!(parse_string("\\x41\\XE9z\""), Some("Aéz".to_string()));
+        assert_eq!(
+            parse_string("\\101\\0z\\1012\""),
+            Some("A\0zA2".to_string())
+        );
+        // The closing quote is missing
+        assert_eq!(parse_string("abc"), None);
+        assert_eq!(parse_string("\\x\""), None);
+    }
+
+    #[test]
+    fn keysyms_are_in_the_order_of_their_chars() {
+        assert_eq!(keysym_to_char("space"), Some(' '));
+        assert_eq!(keysym_to_char("asciitilde"), Some('~'));
+        assert_eq!(keysym_to_char("nobreakspace"), Some('\u{A0}'));
+        assert_eq!(keysym_to_char("multiply"), Some('×'));
+        assert_eq!(keysym_to_char("ssharp"), Some('ß'));
+        assert_eq!(keysym_to_char("ydiaeresis"), Some('ÿ'));
+        let chars = (0x20..0x7F).chain(0xA0..=0xFF).filter_map(char::from_u32);
+        assert_eq!(chars.clone().count(), KEYSYMS.len());
+        for (keysym, c) in KEYSYMS.iter().zip(chars) {
+            assert_eq!(keysym_to_char(keysym), Some(c), "{}", keysym);
+        }
+        // Single chars are not looked up in the table
+        for keysym in KEYSYMS.iter().filter(|keysym| keysym.len() == 1) {
+            assert_eq!(keysym_to_char(keysym), keysym.chars().next());
+        }
+    }
+
+    #[test]
+    fn single_key_sequences_are_composed() {
+        let mut engine = ComposeEngine::new();
+        engine.add_sequence(vec![Key::Char('\u{301}')], "´".to_string());
+        assert_eq!(
+            engine.process(Input::Char('\u{301}'), &EngineContext::empty()),
+            vec![
+                EngineAction::Preedit(String::new()),
+                EngineAction::Commit("´".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_including_each_other_are_loaded_once() {
+        let directory = std::env::temp_dir().join(format!("compose-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let first = directory.join("first");
+        let second = directory.join("second");
+        fs::write(
+            &first,
+            format!(
+                "include \"{}\"\n<Multi_key> <a> <e> : \"æ\"\n",
+                second.display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            &second,
+            format!(
+                "include \"{}\"\n<Multi_key> <o> <e> : \"œ\"\n",
+                first.display()
+            ),
+        )
+        .unwrap();
+        let engine = ComposeEngine::from_file(&first);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(engine.unwrap().len(), 2);
+    }
+}
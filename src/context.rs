@@ -0,0 +1,182 @@
+use super::editing::{current_word, is_word};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Maximum length of the surrounding text in bytes
+///
+/// Wayland messages have a maximum length, so longer texts are truncated and only a window around the cursor is sent
+pub const MAX_SURROUNDING_TEXT_LEN: usize = 4000;
+
+/// Part of the surrounding text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub text: String,
+    /// Byte range of the segment within the surrounding text
+    pub range: Range<usize>,
+    /// False if the segment touches an edge of a truncated surrounding text, so it could continue beyond it
+    pub complete: bool,
+}
+
+/// Words and sentence information around the cursor, used for example for predictions and autocorrection
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextContext {
+    /// Word touching the cursor. If the cursor is between two words, it is the word left of the cursor
+    pub current_word: Option<Segment>,
+    /// Words before the current word or the cursor in the order of the text
+    pub previous_words: Vec<Segment>,
+    /// True if no word is between the start of the sentence and the current word or the cursor
+    ///
+    /// If the start of the sentence could be beyond the start of a truncated surrounding text, this is false
+    pub at_sentence_start: bool,
+    /// True if the surrounding text is so long that it was probably truncated by the wayland server
+    pub truncated: bool,
+    /// Position of the cursor in bytes
+    pub cursor: usize,
+}
+
+impl TextContext {
+    /// Extracts the context from 'text' with the cursor at the byte offset 'cursor'
+    ///
+    /// At most 'previous_word_count' words are collected before the current word.
+    /// If the cursor is not within the text or splits a char, the context of an empty text is returned
+    pub fn new(text: &str, cursor: usize, previous_word_count: usize) -> Self {
+        let (text, cursor) = match text.get(..cursor) {
+            Some(_) => (text, cursor),
+            None => ("", 0),
+        };
+        let truncated = may_be_truncated(text);
+        let segment = |range: Range<usize>| Segment {
+            text: text[range.clone()].to_string(),
+            complete: !truncated || (range.start > 0 && range.end < text.len()),
+            range,
+        };
+
+        let current_word = current_word(text, cursor).map(segment);
+        let word_start = current_word
+            .as_ref()
+            .map_or(cursor, |word| word.range.start);
+        let string_left_of_word = &text[..word_start];
+
+        let mut previous_words: Vec<Segment> = string_left_of_word
+            .split_word_bound_indices()
+            .rev()
+            .filter(|(_, word)| is_word(word))
+            .take(previous_word_count)
+            .map(|(index, word)| segment(index..index + word.len()))
+            .collect();
+        previous_words.reverse();
+
+        // The end of the text is always a sentence boundary, so an uppercase letter is appended to find out
+        // if a new sentence would start there
+        let probe = format!("{}X", string_left_of_word);
+        let sentence_start = probe
+            .split_sentence_bound_indices()
+            .last()
+            .map_or(0, |(index, _)| index);
+        let sentence = &string_left_of_word[sentence_start.min(word_start)..];
+        let sentence_is_cut_off = sentence_start == 0 && truncated;
+        let at_sentence_start = !sentence_is_cut_off && !sentence.split_word_bounds().any(is_word);
+
+        TextContext {
+            current_word,
+            previous_words,
+            at_sentence_start,
+            truncated,
+            cursor,
+        }
+    }
+
+    /// Returns the part of the current word left of the cursor, which is the part that was already typed
+    pub fn current_word_prefix(&self) -> &str {
+        match &self.current_word {
+            Some(word) => &word.text[..self.cursor - word.range.start],
+            None => "",
+        }
+    }
+}
+
+/// Returns true if the surrounding text is so long that it was probably truncated by the wayland server
+pub(crate) fn may_be_truncated(text: &str) -> bool {
+    // There is no room for another char, so the text probably continues beyond the window
+    text.len() + 4 > MAX_SURROUNDING_TEXT_LEN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(segments: &[Segment]) -> Vec<&str> {
+        segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn words_around_the_cursor_are_found() {
+        let context = TextContext::new("Schöne Grüße aus Köln", "Schöne Grüße aus Kö".len(), 5);
+        let current_word = context.current_word.clone().unwrap();
+        assert_eq!(current_word.text, "Köln");
+        let start = "Schöne Grüße aus ".len();
+        assert_eq!(current_word.range, start..start + "Köln".len());
+        assert!(current_word.complete);
+        assert_eq!(context.current_word_prefix(), "Kö");
+        assert_eq!(
+            words(&context.previous_words),
+            vec!["Schöne", "Grüße", "aus"]
+        );
+
+        // Between two words, the word left of the cursor is the current word
+        let context = TextContext::new("one two", 3, 5);
+        assert_eq!(context.current_word.unwrap().text, "one");
+        assert!(context.previous_words.is_empty());
+
+        // After whitespace or punctuation, there is no current word
+        let context = TextContext::new("one, two, ", 10, 1);
+        assert_eq!(context.current_word, None);
+        assert_eq!(context.current_word_prefix(), "");
+        assert_eq!(words(&context.previous_words), vec!["two"]);
+    }
+
+    #[test]
+    fn sentence_starts_are_found() {
+        let at_sentence_start =
+            |text: &str| TextContext::new(text, text.len(), 0).at_sentence_start;
+        assert!(at_sentence_start(""));
+        assert!(at_sentence_start("  "));
+        assert!(at_sentence_start("Hello. "));
+        assert!(at_sentence_start("Really? Wh"));
+        assert!(at_sentence_start("Yes!\n"));
+        assert!(!at_sentence_start("Hello "));
+        assert!(!at_sentence_start("Hello wor"));
+    }
+
+    #[test]
+    fn truncated_texts_do_not_start_sentences() {
+        let text = "word ".repeat(MAX_SURROUNDING_TEXT_LEN / 5);
+        let context = TextContext::new(&text, 3, 0);
+        assert!(context.truncated);
+        assert!(!context.at_sentence_start);
+        // The first word could continue before the start of the window
+        assert!(!context.current_word.unwrap().complete);
+        let context = TextContext::new("word ", 3, 0);
+        assert!(!context.truncated);
+        assert!(context.current_word.unwrap().complete);
+    }
+
+    #[test]
+    fn cursors_outside_the_text_or_inside_chars_are_handled() {
+        let empty = TextContext::new("", 0, 2);
+        for (text, cursor) in [("ab", 5), ("ab. cd", 7), ("ä b", 1), ("a ü", 3)] {
+            assert_eq!(
+                TextContext::new(text, cursor, 2),
+                empty,
+                "{} {}",
+                text,
+                cursor
+            );
+        }
+        assert_eq!(empty.current_word, None);
+        assert!(empty.at_sentence_start);
+    }
+}
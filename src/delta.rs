@@ -0,0 +1,142 @@
+use std::cmp;
+use std::ops::Range;
+
+/// Describes how the surrounding text changed between two states of the input method
+///
+/// Applying the delta to the old surrounding text results in the new surrounding text.
+/// This allows connectors to update their own copy of the text without receiving the full text on every change
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextDelta {
+    /// Byte range of the old surrounding text that was removed
+    pub removed: Range<usize>,
+    /// Text that was inserted at the start of the removed range
+    pub inserted: String,
+    /// Position of the cursor in the new surrounding text in bytes
+    pub cursor: usize,
+    /// True if the change only mirrors an edit the input method committed itself
+    ///
+    /// This is only ever set if the EchoMode is EchoMode::Tag
+    pub echo: bool,
+}
+
+impl TextDelta {
+    /// Computes the delta between the old and the new surrounding text
+    ///
+    /// The removed range and the inserted text are kept as small as possible by skipping the common prefix
+    /// and the common suffix of both strings. Both ends of the range are always on char boundaries
+    pub(crate) fn between(old_text: &str, new_text: &str, new_cursor: usize) -> TextDelta {
+        let prefix_len = old_text
+            .char_indices()
+            .zip(new_text.chars())
+            .take_while(|((_, old_char), new_char)| old_char == new_char)
+            .last()
+            .map_or(0, |((index, character), _)| index + character.len_utf8());
+
+        // The suffix must not overlap with the prefix in either of the strings
+        let max_suffix_len = cmp::min(old_text.len(), new_text.len()) - prefix_len;
+        let suffix_len = old_text[prefix_len..]
+            .chars()
+            .rev()
+            .zip(new_text[prefix_len..].chars().rev())
+            .take_while(|(old_char, new_char)| old_char == new_char)
+            .map(|(character, _)| character.len_utf8())
+            .scan(0, |len, char_len| {
+                *len += char_len;
+                Some(*len)
+            })
+            .take_while(|len| *len <= max_suffix_len)
+            .last()
+            .unwrap_or(0);
+
+        TextDelta {
+            removed: prefix_len..old_text.len() - suffix_len,
+            inserted: new_text[prefix_len..new_text.len() - suffix_len].to_string(),
+            cursor: new_cursor,
+            echo: false,
+        }
+    }
+
+    /// Applies the delta to 'text', which has to be the old surrounding text the delta was computed from
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(self.removed.clone(), &self.inserted);
+    }
+}
+
+/// The surrounding text the wayland server confirmed after a commit differs from the text the input method predicted
+///
+/// This happens if the application rejects, reformats or filters the committed text, like numeric fields do.
+/// The input method continues with the confirmed text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Surrounding text the input method expected after its commit
+    pub predicted_text: String,
+    /// Position of the cursor in bytes the input method expected after its commit
+    pub predicted_cursor: usize,
+    /// Surrounding text sent by the wayland server
+    pub confirmed_text: String,
+    /// Position of the cursor in bytes sent by the wayland server
+    pub confirmed_cursor: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that the delta turns the old text into the new one
+    fn checked_delta(old_text: &str, new_text: &str) -> TextDelta {
+        let delta = TextDelta::between(old_text, new_text, 0);
+        let mut text = old_text.to_string();
+        delta.apply(&mut text);
+        assert_eq!(text, new_text);
+        delta
+    }
+
+    #[test]
+    fn insertion_in_ascii_text() {
+        let delta = checked_delta("helo", "hello");
+        assert_eq!(delta.removed, 3..3);
+        assert_eq!(delta.inserted, "l");
+    }
+
+    #[test]
+    fn replacement_in_multibyte_text() {
+        // 'ä' and 'ö' share the first byte, so the range must not end within a char
+        let delta = checked_delta("aäb", "aöb");
+        assert_eq!(delta.removed, 1..3);
+        assert_eq!(delta.inserted, "ö");
+    }
+
+    #[test]
+    fn changes_at_the_start_and_at_the_end() {
+        let delta_start = checked_delta("bc", "abc");
+        assert_eq!(delta_start.removed, 0..0);
+        assert_eq!(delta_start.inserted, "a");
+
+        let delta_end = checked_delta("ab", "abc");
+        assert_eq!(delta_end.removed, 2..2);
+        assert_eq!(delta_end.inserted, "c");
+
+        let deletion = checked_delta("abc", "");
+        assert_eq!(deletion.removed, 0..3);
+        assert_eq!(deletion.inserted, "");
+    }
+
+    #[test]
+    fn repeated_chars_do_not_overlap() {
+        let delta = checked_delta("aa", "aaa");
+        assert_eq!(delta.removed, 2..2);
+        assert_eq!(delta.inserted, "a");
+
+        let delta = checked_delta("ööö", "ö");
+        assert_eq!(delta.removed, 2..6);
+        assert_eq!(delta.inserted, "");
+    }
+
+    #[test]
+    fn unchanged_text_has_an_empty_delta() {
+        let delta = TextDelta::between("aö", "aö", 3);
+        assert_eq!(delta.removed, 3..3);
+        assert_eq!(delta.inserted, "");
+        assert_eq!(delta.cursor, 3);
+    }
+}
@@ -0,0 +1,193 @@
+use super::Transaction;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// High level editing commands, that are translated to requests based on the surrounding text
+///
+/// Words and sentences are found with the Unicode segmentation rules, lines end with a '\n'.
+/// All ranges are computed in bytes, so the requests are accurate for any text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditCommand {
+    /// Deletes the word left of the cursor and the whitespace and punctuation between it and the cursor
+    DeletePreviousWord,
+    /// Deletes the word right of the cursor and the whitespace and punctuation between it and the cursor
+    DeleteNextWord,
+    /// Deletes the text between the start of the sentence and the cursor
+    DeleteToSentenceStart,
+    /// Deletes the text between the start of the line and the cursor or the line break if the cursor is at the start of a line
+    DeleteToLineStart,
+    /// Deletes the text between the cursor and the end of the line or the line break if the cursor is at the end of a line
+    DeleteToLineEnd,
+    /// Replaces the word at the cursor with the text or inserts the text if there is no word at the cursor
+    ReplaceCurrentWord(String),
+}
+
+impl EditCommand {
+    /// Returns the transaction that executes the command on 'text' with the cursor at the byte offset 'cursor'
+    ///
+    /// Returns None if there is nothing to edit or if the cursor is not at a char boundary of the text
+    pub fn to_transaction(&self, text: &str, cursor: usize) -> Option<Transaction> {
+        let string_left_of_cursor = text.get(..cursor)?;
+        let string_right_of_cursor = text.get(cursor..)?;
+        let range = match self {
+            EditCommand::DeletePreviousWord => previous_word_start(string_left_of_cursor)..cursor,
+            EditCommand::DeleteNextWord => cursor..cursor + next_word_end(string_right_of_cursor),
+            EditCommand::DeleteToSentenceStart => sentence_start(string_left_of_cursor)..cursor,
+            EditCommand::DeleteToLineStart => line_start(string_left_of_cursor)..cursor,
+            EditCommand::DeleteToLineEnd => cursor..cursor + line_end(string_right_of_cursor),
+            EditCommand::ReplaceCurrentWord(replacement) => {
+                let range = current_word(text, cursor).unwrap_or(cursor..cursor);
+                return Some(delete_range(range, cursor).commit_string(replacement.clone()));
+            }
+        };
+        match range.is_empty() {
+            true => None,
+            false => Some(delete_range(range, cursor)),
+        }
+    }
+}
+
+/// Returns a transaction that deletes the byte range, which has to contain the cursor
+fn delete_range(range: Range<usize>, cursor: usize) -> Transaction {
+    Transaction::new().delete_surrounding_text(cursor - range.start, range.end - cursor)
+}
+
+/// Returns true if the segment is a word and not whitespace or punctuation
+pub(crate) fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+/// Returns the byte range of the word touching the cursor
+///
+/// If the cursor is between two words, the word left of the cursor is returned
+pub(crate) fn current_word(text: &str, cursor: usize) -> Option<Range<usize>> {
+    text.split_word_bound_indices()
+        .map(|(index, segment)| index..index + segment.len())
+        .filter(|range| range.start <= cursor && cursor <= range.end)
+        .find(|range| is_word(&text[range.clone()]))
+}
+
+/// Returns the start of the first word left of the end of the text and of everything after it
+fn previous_word_start(text: &str) -> usize {
+    let mut start = text.len();
+    for (index, segment) in text.split_word_bound_indices().rev() {
+        start = index;
+        if is_word(segment) {
+            break;
+        }
+    }
+    start
+}
+
+/// Returns the end of the first word right of the start of the text and of everything before it
+fn next_word_end(text: &str) -> usize {
+    let mut end = 0;
+    for (index, segment) in text.split_word_bound_indices() {
+        end = index + segment.len();
+        if is_word(segment) {
+            break;
+        }
+    }
+    end
+}
+
+/// Returns the start of the last sentence in the text
+fn sentence_start(text: &str) -> usize {
+    text.split_sentence_bound_indices()
+        .last()
+        .map_or(0, |(index, _)| index)
+}
+
+/// Returns the start of the last line in the text or the position of the line break if the text ends with it
+fn line_start(text: &str) -> usize {
+    match text.strip_suffix('\n') {
+        Some(text) => text.len(),
+        None => text.rfind('\n').map_or(0, |index| index + 1),
+    }
+}
+
+/// Returns the end of the first line in the text or the end of the line break if the text starts with it
+fn line_end(text: &str) -> usize {
+    match text.starts_with('\n') {
+        true => 1,
+        false => text.find('\n').unwrap_or(text.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delete(before: usize, after: usize) -> Option<Transaction> {
+        Some(Transaction::new().delete_surrounding_text(before, after))
+    }
+
+    #[test]
+    fn words_are_deleted_in_bytes() {
+        let text = "Grüße, schöne Welt";
+        // The cursor is after "schöne"
+        let cursor = "Grüße, schöne".len();
+        assert_eq!(
+            EditCommand::DeletePreviousWord.to_transaction(text, cursor),
+            delete("schöne".len(), 0)
+        );
+        assert_eq!(
+            EditCommand::DeletePreviousWord.to_transaction(text, "Grüße, ".len()),
+            delete("Grüße, ".len(), 0)
+        );
+        assert_eq!(
+            EditCommand::DeleteNextWord.to_transaction(text, "Grüße".len()),
+            delete(0, ", schöne".len())
+        );
+        assert_eq!(
+            EditCommand::DeleteNextWord.to_transaction(text, text.len()),
+            None
+        );
+        assert_eq!(
+            EditCommand::DeletePreviousWord.to_transaction(text, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn lines_are_deleted_in_bytes() {
+        let text = "première ligne\nzweite Zeile ü\n";
+        let cursor = "première ligne\nzweite".len();
+        assert_eq!(
+            EditCommand::DeleteToLineStart.to_transaction(text, cursor),
+            delete("zweite".len(), 0)
+        );
+        assert_eq!(
+            EditCommand::DeleteToLineEnd.to_transaction(text, cursor),
+            delete(0, " Zeile ü".len())
+        );
+        // At the start or end of a line, the line break is deleted
+        let start = "première ligne\n".len();
+        assert_eq!(
+            EditCommand::DeleteToLineStart.to_transaction(text, start),
+            delete(1, 0)
+        );
+        assert_eq!(
+            EditCommand::DeleteToLineEnd.to_transaction(text, start - 1),
+            delete(0, 1)
+        );
+        assert_eq!(EditCommand::DeleteToLineStart.to_transaction(text, 0), None);
+        assert_eq!(
+            EditCommand::DeleteToLineEnd.to_transaction(text, text.len()),
+            None
+        );
+    }
+
+    #[test]
+    fn cursors_inside_chars_or_out_of_range_are_rejected() {
+        let text = "aü";
+        for command in [
+            EditCommand::DeletePreviousWord,
+            EditCommand::DeleteToLineEnd,
+            EditCommand::ReplaceCurrentWord("b".to_string()),
+        ] {
+            assert_eq!(command.to_transaction(text, 2), None);
+            assert_eq!(command.to_transaction(text, 4), None);
+        }
+    }
+}
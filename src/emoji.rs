@@ -0,0 +1,353 @@
+use super::{EngineAction, EngineContext, Input, InputEngine};
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ContentPurpose;
+
+/// Maximum number of emoji offered as candidates
+const MAX_CANDIDATES: usize = 20;
+
+/// Minimum number of typed chars of a shortcode before candidates are offered
+const MIN_PREFIX_LEN: usize = 2;
+
+/// Skin tone of emoji like hands or people
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SkinTone {
+    /// The yellow default of the emoji
+    #[default]
+    Default,
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+impl From<SkinTone> for emojis::SkinTone {
+    fn from(skin_tone: SkinTone) -> Self {
+        match skin_tone {
+            SkinTone::Default => emojis::SkinTone::Default,
+            SkinTone::Light => emojis::SkinTone::Light,
+            SkinTone::MediumLight => emojis::SkinTone::MediumLight,
+            SkinTone::Medium => emojis::SkinTone::Medium,
+            SkinTone::MediumDark => emojis::SkinTone::MediumDark,
+            SkinTone::Dark => emojis::SkinTone::Dark,
+        }
+    }
+}
+
+/// Engine that replaces shortcodes like ':smile:' with emoji
+///
+/// A typed ':' starts a shortcode, which is shown as the preedit string while the emoji with matching shortcodes
+/// are offered as candidates. The closing ':' commits the emoji and any other char commits the typed text unchanged.
+/// If the closing ':' is typed after a shortcode that is already part of the surrounding text, the shortcode is
+/// deleted and replaced by the emoji with a single commit.
+///
+/// The shortcodes are those of GitHub's gemoji and the emoji are ordered like in the Unicode CLDR data.
+/// The engine is disabled in text inputs for sensitive data or with a purpose other than ContentPurpose::Normal
+#[derive(Clone, Debug, Default)]
+pub struct EmojiEngine {
+    skin_tone: SkinTone,
+    // Chars of the shortcode typed after the ':' or None if no shortcode is typed
+    shortcode: Option<String>,
+    candidates: Vec<String>,
+}
+
+impl EmojiEngine {
+    /// Creates a new EmojiEngine that commits emoji with the default skin tone
+    pub fn new() -> Self {
+        EmojiEngine::default()
+    }
+
+    /// Sets the skin tone of committed emoji and of the first candidate. The other skin tones are still offered
+    pub fn with_skin_tone(mut self, skin_tone: SkinTone) -> Self {
+        self.skin_tone = skin_tone;
+        self
+    }
+
+    /// Returns the emoji with the preferred skin tone, if the emoji has skin tones
+    fn emoji_with_skin_tone(&self, emoji: &emojis::Emoji) -> String {
+        emoji
+            .with_skin_tone(self.skin_tone.into())
+            .unwrap_or(emoji)
+            .as_str()
+            .to_string()
+    }
+
+    /// Returns the emoji for the complete shortcode
+    fn lookup(&self, shortcode: &str) -> Option<String> {
+        emojis::get_by_shortcode(shortcode).map(|emoji| self.emoji_with_skin_tone(emoji))
+    }
+
+    /// Returns the emoji with shortcodes that start with the typed chars
+    ///
+    /// The emoji of an exact match comes first followed by its skin tones. The others are ordered by the length of their shortcode
+    fn find_candidates(&self, prefix: &str) -> Vec<String> {
+        let mut candidates = Vec::new();
+        if prefix.len() < MIN_PREFIX_LEN {
+            return candidates;
+        }
+        if let Some(emoji) = emojis::get_by_shortcode(prefix) {
+            candidates.push(self.emoji_with_skin_tone(emoji));
+            // Skin tones of emoji with several people are not offered
+            let skin_tones = emoji.skin_tones().into_iter().flatten().filter(|emoji| {
+                matches!(
+                    emoji.skin_tone(),
+                    Some(
+                        emojis::SkinTone::Default
+                            | emojis::SkinTone::Light
+                            | emojis::SkinTone::MediumLight
+                            | emojis::SkinTone::Medium
+                            | emojis::SkinTone::MediumDark
+                            | emojis::SkinTone::Dark
+                    )
+                )
+            });
+            for emoji in skin_tones {
+                if !candidates
+                    .iter()
+                    .any(|candidate| candidate == emoji.as_str())
+                {
+                    candidates.push(emoji.as_str().to_string());
+                }
+            }
+        }
+        let mut matches: Vec<(usize, &emojis::Emoji)> = emojis::iter()
+            .filter_map(|emoji| {
+                emoji
+                    .shortcodes()
+                    .find(|shortcode| shortcode.starts_with(prefix))
+                    .map(|shortcode| (shortcode.len(), emoji))
+            })
+            .collect();
+        // The sort is stable, so emoji with shortcodes of the same length keep the CLDR order
+        matches.sort_by_key(|(length, _)| *length);
+        for (_, emoji) in matches {
+            let emoji = self.emoji_with_skin_tone(emoji);
+            if !candidates.contains(&emoji) {
+                candidates.push(emoji);
+            }
+        }
+        candidates.truncate(MAX_CANDIDATES);
+        candidates
+    }
+
+    /// Ends the shortcode and returns the actions to remove the preedit string and the candidates
+    fn end(&mut self) -> Vec<EngineAction> {
+        self.shortcode = None;
+        self.candidates.clear();
+        vec![
+            EngineAction::Preedit(String::new()),
+            EngineAction::Candidates(Vec::new()),
+        ]
+    }
+
+    /// Ends the shortcode and commits the typed chars unchanged
+    fn end_raw(&mut self) -> Vec<EngineAction> {
+        let raw = format!(":{}", self.shortcode.take().unwrap_or_default());
+        let mut actions = vec![EngineAction::Commit(raw)];
+        actions.append(&mut self.end());
+        actions
+    }
+
+    /// Ends the shortcode and commits the emoji
+    fn end_with(&mut self, emoji: String) -> Vec<EngineAction> {
+        let mut actions = vec![EngineAction::Commit(emoji)];
+        actions.append(&mut self.end());
+        actions
+    }
+
+    /// Returns the actions to show the typed shortcode and the candidates for it
+    fn update(&mut self, shortcode: String) -> Vec<EngineAction> {
+        self.candidates = self.find_candidates(&shortcode);
+        let actions = vec![
+            EngineAction::Preedit(format!(":{}", shortcode)),
+            EngineAction::Candidates(self.candidates.clone()),
+        ];
+        self.shortcode = Some(shortcode);
+        actions
+    }
+}
+
+impl InputEngine for EmojiEngine {
+    fn process(&mut self, input: Input, context: &EngineContext) -> Vec<EngineAction> {
+        if context.sensitive || context.content_purpose != ContentPurpose::Normal {
+            return vec![EngineAction::Forward(input)];
+        }
+        let shortcode = match self.shortcode.clone() {
+            Some(shortcode) => shortcode,
+            None => {
+                if input != Input::Char(':') {
+                    return vec![EngineAction::Forward(input)];
+                }
+                // Replace a shortcode that is already part of the surrounding text
+                if let Some(shortcode) = trailing_shortcode(&context.text_before_cursor) {
+                    if let Some(emoji) = self.lookup(shortcode) {
+                        return vec![
+                            EngineAction::Delete {
+                                before: shortcode.chars().count() + 1,
+                                after: 0,
+                            },
+                            EngineAction::Commit(emoji),
+                        ];
+                    }
+                }
+                return self.update(String::new());
+            }
+        };
+        match input {
+            Input::Char(':') => match self.lookup(&shortcode) {
+                Some(emoji) => self.end_with(emoji),
+                None => {
+                    // The ':' can start the next shortcode
+                    let mut actions = self.end_raw();
+                    actions.append(&mut self.update(String::new()));
+                    actions
+                }
+            },
+            Input::Char(c) if is_shortcode_char(c) => self.update(shortcode + &c.to_string()),
+            Input::Select(index) => match self.candidates.get(index).cloned() {
+                Some(emoji) => self.end_with(emoji),
+                None => Vec::new(),
+            },
+            Input::Backspace => {
+                let mut shortcode = shortcode;
+                match shortcode.pop() {
+                    Some(_) => self.update(shortcode),
+                    None => self.end(),
+                }
+            }
+            Input::Confirm => match self.lookup(&shortcode) {
+                Some(emoji) => self.end_with(emoji),
+                None => self.end_raw(),
+            },
+            Input::Cancel => self.end_raw(),
+            input => {
+                let mut actions = self.end_raw();
+                actions.push(EngineAction::Forward(input));
+                actions
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.shortcode = None;
+        self.candidates.clear();
+    }
+}
+
+/// Returns true if the char can be part of a shortcode
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+-".contains(c)
+}
+
+/// Returns the shortcode at the end of the text, if it starts with a ':' at the start of a word
+fn trailing_shortcode(text: &str) -> Option<&str> {
+    let start = text.rfind(|c: char| !is_shortcode_char(c))?;
+    let shortcode = text[start..].strip_prefix(':')?;
+    let at_word_start = text[..start]
+        .chars()
+        .next_back()
+        .is_none_or(char::is_whitespace);
+    match at_word_start && !shortcode.is_empty() {
+        true => Some(shortcode),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::type_inputs;
+    use std::collections::HashSet;
+
+    const SMILE: &str = "\u{1F604}";
+    const SMILEY: &str = "\u{1F603}";
+    const THUMBS_UP: &str = "\u{1F44D}";
+    const THUMBS_UP_DARK: &str = "\u{1F44D}\u{1F3FF}";
+
+    #[test]
+    fn trailing_shortcodes_start_words() {
+        assert_eq!(trailing_shortcode(":smile"), Some("smile"));
+        assert_eq!(trailing_shortcode("hi :+1"), Some("+1"));
+        assert_eq!(trailing_shortcode("a:smile"), None);
+        assert_eq!(trailing_shortcode("12:30"), None);
+        assert_eq!(trailing_shortcode("hi :"), None);
+        assert_eq!(trailing_shortcode("smile"), None);
+    }
+
+    #[test]
+    fn shortcodes_in_the_surrounding_text_are_replaced() {
+        let mut engine = EmojiEngine::new();
+        let context = EngineContext {
+            text_before_cursor: "hi :smile".to_string(),
+            ..EngineContext::empty()
+        };
+        assert_eq!(
+            engine.process(Input::Char(':'), &context),
+            vec![
+                EngineAction::Delete {
+                    before: 6,
+                    after: 0
+                },
+                EngineAction::Commit(SMILE.to_string()),
+            ]
+        );
+        // An unknown shortcode is kept and the ':' starts a new one
+        let context = EngineContext {
+            text_before_cursor: "hi :unknown_emoji".to_string(),
+            ..EngineContext::empty()
+        };
+        assert_eq!(
+            engine.process(Input::Char(':'), &context),
+            vec![
+                EngineAction::Preedit(":".to_string()),
+                EngineAction::Candidates(Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn typed_shortcodes_are_replaced() {
+        let mut engine = EmojiEngine::new();
+        let inputs = ":smile:".chars().map(Input::Char).collect();
+        assert_eq!(
+            type_inputs(&mut engine, inputs),
+            (SMILE.to_string(), String::new())
+        );
+        let inputs = ":nope ".chars().map(Input::Char).collect();
+        assert_eq!(
+            type_inputs(&mut engine, inputs),
+            (":nope ".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn candidates_are_ordered_by_shortcode_length() {
+        let engine = EmojiEngine::new();
+        assert_eq!(engine.find_candidates("s"), Vec::<String>::new());
+        let candidates = engine.find_candidates("smil");
+        assert_eq!(candidates[..2], [SMILE.to_string(), SMILEY.to_string()]);
+        assert!(candidates.len() <= MAX_CANDIDATES);
+        let unique: HashSet<&String> = candidates.iter().collect();
+        assert_eq!(unique.len(), candidates.len());
+    }
+
+    #[test]
+    fn exact_matches_come_first_with_their_skin_tones() {
+        let engine = EmojiEngine::new().with_skin_tone(SkinTone::Dark);
+        let candidates = engine.find_candidates("+1");
+        assert_eq!(candidates[0], THUMBS_UP_DARK);
+        // The default and the other four skin tones follow without the preferred one again
+        assert_eq!(candidates[1], THUMBS_UP);
+        assert_eq!(candidates.len(), 6);
+        let unique: HashSet<&String> = candidates.iter().collect();
+        assert_eq!(unique.len(), candidates.len());
+    }
+
+    #[test]
+    fn skin_tones_only_apply_to_emoji_that_have_them() {
+        let engine = EmojiEngine::new().with_skin_tone(SkinTone::Dark);
+        assert_eq!(engine.lookup("smile"), Some(SMILE.to_string()));
+        assert_eq!(engine.lookup("+1"), Some(THUMBS_UP_DARK.to_string()));
+        assert_eq!(engine.find_candidates("smile")[0], SMILE);
+        assert_eq!(engine.lookup("unknown_emoji"), None);
+    }
+}
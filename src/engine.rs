@@ -0,0 +1,314 @@
+use super::{
+    HintPurpose, IMService, IMServiceArc, IMVisibility, Preedit, ReceiveSurroundingText,
+    SubmitError, Transaction,
+};
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
+
+/// Input of the user or of a previous engine of the pipeline
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// A char was typed
+    Char(char),
+    /// Text that was committed by a previous engine of the pipeline
+    Text(String),
+    /// The backspace key was pressed
+    Backspace,
+    /// The return key was pressed. Engines usually commit their preedit string
+    Confirm,
+    /// The escape key was pressed. Engines usually discard their preedit string
+    Cancel,
+    /// The candidate at the index of the last candidate list was selected
+    Select(usize),
+    /// Any other key with its linux input event code
+    Key(u32),
+}
+
+/// Result of an engine processing an input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EngineAction {
+    /// Passes the input to the next engine of the pipeline
+    Forward(Input),
+    /// Commits the text. The next engine of the pipeline receives it as Input::Text
+    Commit(String),
+    /// Replaces the preedit string of the engine. An empty string removes it
+    Preedit(String),
+    /// Deletes chars of the surrounding text around the cursor
+    Delete { before: usize, after: usize },
+    /// Replaces the candidates shown to the user. An empty list hides them
+    Candidates(Vec<String>),
+}
+
+/// Information about the text input the engines can use to process an input
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineContext {
+    /// Surrounding text left of the cursor
+    pub text_before_cursor: String,
+    /// Surrounding text right of the cursor
+    pub text_after_cursor: String,
+    pub content_hint: ContentHint,
+    pub content_purpose: ContentPurpose,
+    /// If this is set, engines must not store or learn any text
+    pub sensitive: bool,
+}
+
+/// Engine that composes text from the inputs of the user, like a transliteration or a prediction
+pub trait InputEngine {
+    /// Processes the input and returns the resulting actions in the order they should be executed
+    ///
+    /// Engines should forward every input they do not handle, so the following engines receive it
+    fn process(&mut self, input: Input, context: &EngineContext) -> Vec<EngineAction>;
+
+    /// Discards the state of the engine, for example the text it is composing
+    ///
+    /// This is called when the text input changed, so the state is not valid anymore
+    fn reset(&mut self) {}
+}
+
+/// Chain of engines that turns inputs of the user into requests of an IMService
+///
+/// Each engine receives the inputs forwarded and the text committed by the previous engine.
+/// Inputs forwarded by the last engine are executed: chars and texts are committed, a backspace deletes the char
+/// left of the cursor and all other inputs are returned to the caller, for example to send the key.
+///
+/// The text committed by an engine is processed by the following engines, so its preedit string is older
+/// than theirs. That's why the preedit strings are shown in the reverse order of the engines
+#[derive(Default)]
+pub struct Pipeline {
+    engines: Vec<Box<dyn InputEngine + Send>>,
+    preedits: Vec<String>,
+    candidates: Vec<String>,
+    // Index of the engine that sent the current candidates
+    candidate_owner: Option<usize>,
+    // Activation of the IMService the state of the engines belongs to
+    activation: Option<u64>,
+}
+
+impl Pipeline {
+    /// Creates a new Pipeline without engines. It commits every char unchanged
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Appends the engine to the end of the pipeline
+    pub fn with<E: InputEngine + Send + 'static>(mut self, engine: E) -> Self {
+        self.engines.push(Box::new(engine));
+        self.preedits.push(String::new());
+        self
+    }
+
+    /// Returns the candidates that are currently shown
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// Returns the combined preedit string of all engines
+    pub fn preedit(&self) -> String {
+        self.preedits.iter().rev().map(String::as_str).collect()
+    }
+
+    /// Resets all engines and discards their preedit strings and the candidates
+    ///
+    /// This should be called when the input method gets deactivated
+    pub fn reset(&mut self) {
+        for engine in &mut self.engines {
+            engine.reset();
+        }
+        self.preedits.iter_mut().for_each(String::clear);
+        self.candidates.clear();
+        self.candidate_owner = None;
+    }
+
+    /// Processes the input and submits the resulting requests to the IMService with a single commit
+    ///
+    /// The engines are reset first if another text input was activated since the last input, so their state
+    /// does not carry over. If the candidates change, they are passed to IMVisibility::show_candidates().
+    /// Returns the inputs that were forwarded by the last engine but could not be executed as requests,
+    /// like keys that have to be sent with a virtual keyboard
+    pub fn process<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText>(
+        &mut self,
+        im_service: &IMService<T, D>,
+        input: Input,
+    ) -> Result<Vec<Input>, SubmitError> {
+        let mut im_service_arc = im_service.im_service_arc.lock().unwrap();
+        let candidates = self.candidates.clone();
+        if !im_service_arc.is_active() {
+            self.reset();
+            if !candidates.is_empty() {
+                im_service_arc.show_candidates(&[]);
+            }
+            return Err(SubmitError::NotActive);
+        }
+        let activation = im_service_arc.activation();
+        if self.activation != Some(activation) {
+            self.reset();
+            self.activation = Some(activation);
+        }
+        let context = engine_context(&im_service_arc);
+        let preedit = self.preedit();
+        let mut output = Output::default();
+        // The candidates are selected directly at the engine that sent them
+        match (&input, self.candidate_owner) {
+            (Input::Select(_), Some(owner)) => self.process_at(owner, input, &context, &mut output),
+            _ => self.process_at(0, input, &context, &mut output),
+        }
+        if self.candidates != candidates {
+            im_service_arc.show_candidates(&self.candidates);
+        }
+        let transaction = output.to_transaction(&context);
+        // Only submit a transaction if the surrounding text or the preedit string changed
+        if !transaction.is_empty() || self.preedit() != preedit {
+            let transaction = transaction.preedit(Preedit::new(self.preedit()));
+            im_service_arc.submit(transaction)?;
+        }
+        Ok(output.unhandled)
+    }
+
+    /// Passes the input to the engine at 'index' and the resulting actions to the following engines
+    fn process_at(
+        &mut self,
+        index: usize,
+        input: Input,
+        context: &EngineContext,
+        output: &mut Output,
+    ) {
+        let engine = match self.engines.get_mut(index) {
+            Some(engine) => engine,
+            None => return output.execute(input),
+        };
+        for action in engine.process(input, context) {
+            match action {
+                EngineAction::Forward(input) => self.process_at(index + 1, input, context, output),
+                EngineAction::Commit(text) => {
+                    self.process_at(index + 1, Input::Text(text), context, output)
+                }
+                EngineAction::Preedit(text) => self.preedits[index] = text,
+                EngineAction::Delete { before, after } => output.delete(before, after),
+                EngineAction::Candidates(candidates) => {
+                    self.candidate_owner = match candidates.is_empty() {
+                        true => None,
+                        false => Some(index),
+                    };
+                    self.candidates = candidates;
+                }
+            }
+        }
+    }
+}
+
+/// Requests collected while the engines process an input
+#[derive(Default)]
+struct Output {
+    // Chars to delete left and right of the cursor from the surrounding text
+    delete_before: usize,
+    delete_after: usize,
+    commit_string: String,
+    unhandled: Vec<Input>,
+}
+
+impl Output {
+    /// Executes an input forwarded by the last engine
+    fn execute(&mut self, input: Input) {
+        match input {
+            Input::Char(c) => self.commit_string.push(c),
+            Input::Text(text) => self.commit_string.push_str(&text),
+            Input::Backspace => self.delete(1, 0),
+            // A selection without candidates is ignored
+            Input::Select(_) => (),
+            input => self.unhandled.push(input),
+        }
+    }
+
+    /// Deletes chars left and right of the cursor
+    ///
+    /// Chars left of the cursor are first removed from the text committed by the same input
+    fn delete(&mut self, before: usize, after: usize) {
+        for _ in 0..before {
+            if self.commit_string.pop().is_none() {
+                self.delete_before += 1;
+            }
+        }
+        self.delete_after += after;
+    }
+
+    /// Returns the transaction for the requests, which converts the chars to delete to bytes of the surrounding text
+    fn to_transaction(&self, context: &EngineContext) -> Transaction {
+        let mut transaction = Transaction::new();
+        if self.delete_before > 0 || self.delete_after > 0 {
+            let before = context
+                .text_before_cursor
+                .chars()
+                .rev()
+                .take(self.delete_before)
+                .map(char::len_utf8)
+                .sum();
+            let after = context
+                .text_after_cursor
+                .chars()
+                .take(self.delete_after)
+                .map(char::len_utf8)
+                .sum();
+            transaction = transaction.delete_surrounding_text(before, after);
+        }
+        if !self.commit_string.is_empty() {
+            transaction = transaction.commit_string(self.commit_string.clone());
+        }
+        transaction
+    }
+}
+
+/// Returns the context of the current text input for the engines
+///
+/// Only the current state is used, because the wayland server could have sent parts of the next state already
+fn engine_context<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText>(
+    im_service_arc: &IMServiceArc<T, D>,
+) -> EngineContext {
+    let (text_before_cursor, text_after_cursor) = im_service_arc.get_surrounding_text();
+    let (content_hint, content_purpose) = im_service_arc.content_type();
+    EngineContext {
+        text_before_cursor,
+        text_after_cursor,
+        content_hint,
+        content_purpose,
+        sensitive: im_service_arc.is_sensitive(),
+    }
+}
+
+#[cfg(test)]
+impl EngineContext {
+    /// Returns the context of an empty text input with the default content type
+    pub(crate) fn empty() -> Self {
+        EngineContext {
+            text_before_cursor: String::new(),
+            text_after_cursor: String::new(),
+            content_hint: ContentHint::None,
+            content_purpose: ContentPurpose::Normal,
+            sensitive: false,
+        }
+    }
+}
+
+/// Passes the inputs to the engine in an empty text input and returns the committed text and the last preedit string
+///
+/// Forwarded chars and texts are committed, other forwarded inputs are appended in their debug format
+#[cfg(test)]
+pub(crate) fn type_inputs<E: InputEngine>(engine: &mut E, inputs: Vec<Input>) -> (String, String) {
+    let context = EngineContext::empty();
+    let mut committed = String::new();
+    let mut preedit = String::new();
+    for input in inputs {
+        for action in engine.process(input, &context) {
+            match action {
+                EngineAction::Commit(text) | EngineAction::Forward(Input::Text(text)) => {
+                    committed.push_str(&text)
+                }
+                EngineAction::Forward(Input::Char(c)) => committed.push(c),
+                EngineAction::Forward(input) => committed.push_str(&format!("{:?}", input)),
+                EngineAction::Preedit(text) => preedit = text,
+                _ => (),
+            }
+        }
+    }
+    (committed, preedit)
+}
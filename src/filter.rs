@@ -0,0 +1,166 @@
+use super::SubmitError;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ContentPurpose;
+
+/// Decides what happens to committed text that is not valid for the content purpose of the text input
+///
+/// Applications may reject or misinterpret text that does not fit the purpose, like letters in a field for digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    /// The text is committed unchanged (default)
+    #[default]
+    Off,
+    /// The commit fails with SubmitError::InvalidInput if the text contains an invalid char
+    Reject,
+    /// Chars are normalized, for example full width digits to ASCII digits, and invalid chars are removed
+    Transform,
+}
+
+impl FilterMode {
+    /// Checks or transforms the text for the content purpose
+    pub fn apply(self, purpose: ContentPurpose, text: String) -> Result<String, SubmitError> {
+        match self {
+            FilterMode::Off => Ok(text),
+            FilterMode::Reject => match text.chars().find(|c| !is_allowed(purpose, *c)) {
+                Some(character) => Err(SubmitError::InvalidInput { purpose, character }),
+                None => Ok(text),
+            },
+            FilterMode::Transform => Ok(text
+                .chars()
+                .map(|c| normalize(purpose, c))
+                .filter(|c| is_allowed(purpose, *c))
+                .collect()),
+        }
+    }
+}
+
+/// Returns true if the char is valid in a text input with the content purpose
+fn is_allowed(purpose: ContentPurpose, c: char) -> bool {
+    match purpose {
+        ContentPurpose::Alpha => c.is_alphabetic(),
+        ContentPurpose::Digits | ContentPurpose::Pin => c.is_ascii_digit(),
+        ContentPurpose::Number => c.is_ascii_digit() || "+-.,eE".contains(c),
+        ContentPurpose::Phone => c.is_ascii_digit() || "+*#()-. ".contains(c),
+        ContentPurpose::Email | ContentPurpose::Url => !c.is_whitespace(),
+        ContentPurpose::Date => c.is_ascii_digit() || "-/. ".contains(c),
+        ContentPurpose::Time => c.is_ascii_digit() || ":. aApPmM".contains(c),
+        ContentPurpose::Datetime => c.is_ascii_digit() || "-/.:, TaApPmM".contains(c),
+        _ => true,
+    }
+}
+
+/// Replaces chars that have an equivalent, that is valid for the content purpose
+///
+/// Full width forms and other decimal digits are replaced with ASCII digits and different dashes with a hyphen
+fn normalize(purpose: ContentPurpose, c: char) -> char {
+    match purpose {
+        ContentPurpose::Normal
+        | ContentPurpose::Alpha
+        | ContentPurpose::Name
+        | ContentPurpose::Password
+        | ContentPurpose::Terminal => c,
+        _ => match c {
+            // Full width forms of the ASCII chars
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap_or(c),
+            // Hyphens, dashes and the minus sign
+            '\u{2010}'..='\u{2015}' | '\u{2212}' => '-',
+            '\u{3000}' => ' ',
+            _ => decimal_digit(c).unwrap_or(c),
+        },
+    }
+}
+
+/// Returns the ASCII digit for decimal digits of other scripts
+///
+/// Only scripts are covered, where the digits are encoded in a single block from zero to nine
+fn decimal_digit(c: char) -> Option<char> {
+    const ZEROS: [u32; 8] = [
+        0x0660, // Arabic-Indic
+        0x06F0, // Extended Arabic-Indic
+        0x0966, // Devanagari
+        0x09E6, // Bengali
+        0x0E50, // Thai
+        0x1040, // Myanmar
+        0x17E0, // Khmer
+        0x1810, // Mongolian
+    ];
+    ZEROS
+        .iter()
+        .find(|zero| (**zero..**zero + 10).contains(&(c as u32)))
+        .and_then(|zero| char::from_digit(c as u32 - zero, 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_text_is_kept() {
+        let cases = [
+            (ContentPurpose::Digits, "0123"),
+            (ContentPurpose::Number, "-1.5e3"),
+            (ContentPurpose::Phone, "+49 (0) 123-45#"),
+            (ContentPurpose::Email, "a.b@example.com"),
+            (ContentPurpose::Url, "https://example.com/?a=b"),
+            (ContentPurpose::Normal, "Any text, ２ ö"),
+        ];
+        for (purpose, text) in cases {
+            for mode in [FilterMode::Off, FilterMode::Reject, FilterMode::Transform] {
+                assert_eq!(
+                    mode.apply(purpose, text.to_string()),
+                    Ok(text.to_string()),
+                    "{:?} {:?}",
+                    mode,
+                    purpose
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_chars_are_rejected() {
+        let cases = [
+            (ContentPurpose::Digits, "12a", 'a'),
+            (ContentPurpose::Digits, "1.5", '.'),
+            (ContentPurpose::Number, "1x", 'x'),
+            (ContentPurpose::Phone, "123 abc", 'a'),
+            (ContentPurpose::Email, "a b@c", ' '),
+            (ContentPurpose::Url, "a\tb", '\t'),
+            // Chars are checked before they are normalized
+            (ContentPurpose::Digits, "１", '１'),
+        ];
+        for (purpose, text, character) in cases {
+            assert_eq!(
+                FilterMode::Reject.apply(purpose, text.to_string()),
+                Err(SubmitError::InvalidInput { purpose, character }),
+                "{}",
+                text
+            );
+            assert_eq!(
+                FilterMode::Off.apply(purpose, text.to_string()),
+                Ok(text.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn chars_are_normalized_and_invalid_chars_removed() {
+        let cases = [
+            (ContentPurpose::Digits, "１２３", "123"),
+            (ContentPurpose::Digits, "٤٢ apples", "42"),
+            (ContentPurpose::Number, "−1，5", "-1,5"),
+            (ContentPurpose::Phone, "＋49　१२३–4", "+49 123-4"),
+            (ContentPurpose::Email, "ａ@ｂ.de ", "a@b.de"),
+            (ContentPurpose::Url, "ｅｘａｍｐｌｅ．ｃｏｍ", "example.com"),
+            // Text inputs for free text are not normalized
+            (ContentPurpose::Normal, "１２３", "１２３"),
+        ];
+        for (purpose, text, expected) in cases {
+            assert_eq!(
+                FilterMode::Transform.apply(purpose, text.to_string()),
+                Ok(expected.to_string()),
+                "{}",
+                text
+            );
+        }
+    }
+}
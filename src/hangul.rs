@@ -0,0 +1,401 @@
+use super::{EngineAction, EngineContext, Input, InputEngine};
+
+/// Initial consonants in the order of the Hangul syllables block
+const CHOSEONG: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+
+/// Vowels in the order of the Hangul syllables block
+const JUNGSEONG: [char; 21] = [
+    'ㅏ', 'ㅐ', 'ㅑ', 'ㅒ', 'ㅓ', 'ㅔ', 'ㅕ', 'ㅖ', 'ㅗ', 'ㅘ', 'ㅙ', 'ㅚ', 'ㅛ', 'ㅜ', 'ㅝ', 'ㅞ',
+    'ㅟ', 'ㅠ', 'ㅡ', 'ㅢ', 'ㅣ',
+];
+
+/// Final consonants in the order of the Hangul syllables block, which starts with the syllable without one
+const JONGSEONG: [char; 27] = [
+    'ㄱ', 'ㄲ', 'ㄳ', 'ㄴ', 'ㄵ', 'ㄶ', 'ㄷ', 'ㄹ', 'ㄺ', 'ㄻ', 'ㄼ', 'ㄽ', 'ㄾ', 'ㄿ', 'ㅀ', 'ㅁ',
+    'ㅂ', 'ㅄ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅊ', 'ㅋ', 'ㅌ', 'ㅍ', 'ㅎ',
+];
+
+/// Vowels that are combined from two vowels
+const COMPOUND_VOWELS: [(char, char, char); 7] = [
+    ('ㅗ', 'ㅏ', 'ㅘ'),
+    ('ㅗ', 'ㅐ', 'ㅙ'),
+    ('ㅗ', 'ㅣ', 'ㅚ'),
+    ('ㅜ', 'ㅓ', 'ㅝ'),
+    ('ㅜ', 'ㅔ', 'ㅞ'),
+    ('ㅜ', 'ㅣ', 'ㅟ'),
+    ('ㅡ', 'ㅣ', 'ㅢ'),
+];
+
+/// Final consonants that are combined from two consonants
+const COMPOUND_FINALS: [(char, char, char); 11] = [
+    ('ㄱ', 'ㅅ', 'ㄳ'),
+    ('ㄴ', 'ㅈ', 'ㄵ'),
+    ('ㄴ', 'ㅎ', 'ㄶ'),
+    ('ㄹ', 'ㄱ', 'ㄺ'),
+    ('ㄹ', 'ㅁ', 'ㄻ'),
+    ('ㄹ', 'ㅂ', 'ㄼ'),
+    ('ㄹ', 'ㅅ', 'ㄽ'),
+    ('ㄹ', 'ㅌ', 'ㄾ'),
+    ('ㄹ', 'ㅍ', 'ㄿ'),
+    ('ㄹ', 'ㅎ', 'ㅀ'),
+    ('ㅂ', 'ㅅ', 'ㅄ'),
+];
+
+/// Jamo of the keys of the 2-set layout from 'a' to 'z'
+const LAYOUT: [char; 26] = [
+    'ㅁ', 'ㅠ', 'ㅊ', 'ㅇ', 'ㄷ', 'ㄹ', 'ㅎ', 'ㅗ', 'ㅑ', 'ㅓ', 'ㅏ', 'ㅣ', 'ㅡ', 'ㅜ', 'ㅐ', 'ㅔ',
+    'ㅂ', 'ㄱ', 'ㄴ', 'ㅅ', 'ㅕ', 'ㅍ', 'ㅈ', 'ㅌ', 'ㅛ', 'ㅋ',
+];
+
+/// Jamo of the keys of the 2-set layout, that differ with the shift key
+const SHIFTED_LAYOUT: [(char, char); 7] = [
+    ('Q', 'ㅃ'),
+    ('W', 'ㅉ'),
+    ('E', 'ㄸ'),
+    ('R', 'ㄲ'),
+    ('T', 'ㅆ'),
+    ('O', 'ㅒ'),
+    ('P', 'ㅖ'),
+];
+
+/// Linux input event codes of the letter keys with their lowercase letters
+const LETTER_KEYS: [(u32, char); 26] = [
+    (input_event_codes::KEY_A!(), 'a'),
+    (input_event_codes::KEY_B!(), 'b'),
+    (input_event_codes::KEY_C!(), 'c'),
+    (input_event_codes::KEY_D!(), 'd'),
+    (input_event_codes::KEY_E!(), 'e'),
+    (input_event_codes::KEY_F!(), 'f'),
+    (input_event_codes::KEY_G!(), 'g'),
+    (input_event_codes::KEY_H!(), 'h'),
+    (input_event_codes::KEY_I!(), 'i'),
+    (input_event_codes::KEY_J!(), 'j'),
+    (input_event_codes::KEY_K!(), 'k'),
+    (input_event_codes::KEY_L!(), 'l'),
+    (input_event_codes::KEY_M!(), 'm'),
+    (input_event_codes::KEY_N!(), 'n'),
+    (input_event_codes::KEY_O!(), 'o'),
+    (input_event_codes::KEY_P!(), 'p'),
+    (input_event_codes::KEY_Q!(), 'q'),
+    (input_event_codes::KEY_R!(), 'r'),
+    (input_event_codes::KEY_S!(), 's'),
+    (input_event_codes::KEY_T!(), 't'),
+    (input_event_codes::KEY_U!(), 'u'),
+    (input_event_codes::KEY_V!(), 'v'),
+    (input_event_codes::KEY_W!(), 'w'),
+    (input_event_codes::KEY_X!(), 'x'),
+    (input_event_codes::KEY_Y!(), 'y'),
+    (input_event_codes::KEY_Z!(), 'z'),
+];
+
+/// Syllable that is composed from jamo
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Syllable {
+    choseong: Option<char>,
+    jungseong: Option<char>,
+    jongseong: Option<char>,
+}
+
+impl Syllable {
+    /// Returns the syllable as a precomposed char or the single jamo if it is incomplete
+    fn to_char(self) -> Option<char> {
+        let position = |table: &[char], jamo| table.iter().position(|c| *c == jamo);
+        match (self.choseong, self.jungseong, self.jongseong) {
+            (Some(choseong), Some(jungseong), jongseong) => {
+                let choseong = position(&CHOSEONG, choseong)?;
+                let jungseong = position(&JUNGSEONG, jungseong)?;
+                let jongseong = match jongseong {
+                    Some(jongseong) => position(&JONGSEONG, jongseong)? + 1,
+                    None => 0,
+                };
+                char::from_u32(0xAC00 + ((choseong * 21 + jungseong) * 28 + jongseong) as u32)
+            }
+            (choseong, jungseong, _) => choseong.or(jungseong),
+        }
+    }
+}
+
+/// Engine that composes Hangul syllables from jamo with the 2-set (Dubeolsik) layout
+///
+/// The jamo can be typed as Hangul compatibility jamo like 'ㄱ' from an on-screen keyboard,
+/// as Latin letters of the keys of the 2-set layout or as keys of a grabbed keyboard.
+/// Keys can not be shifted, so double consonants have to be typed as chars.
+///
+/// The composing syllable is shown as the preedit string and committed when the next jamo can not be added to it.
+/// A backspace removes the last typed jamo, so compound vowels and final consonants are decomposed one at a time
+#[derive(Clone, Debug, Default)]
+pub struct HangulEngine {
+    // States of the composing syllable after each typed jamo
+    states: Vec<Syllable>,
+}
+
+impl HangulEngine {
+    /// Creates a new HangulEngine
+    pub fn new() -> Self {
+        HangulEngine::default()
+    }
+
+    /// Returns the composing syllable
+    fn syllable(&self) -> Syllable {
+        self.states.last().copied().unwrap_or_default()
+    }
+
+    /// Returns the preedit string for the composing syllable
+    fn preedit(&self) -> EngineAction {
+        EngineAction::Preedit(
+            self.syllable()
+                .to_char()
+                .map(String::from)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Commits the composing syllable and starts the next one with the states
+    fn commit(&mut self, syllable: Syllable, states: Vec<Syllable>) -> Vec<EngineAction> {
+        self.states = states;
+        let mut actions = vec![self.preedit()];
+        if let Some(c) = syllable.to_char() {
+            actions.push(EngineAction::Commit(c.to_string()));
+        }
+        actions
+    }
+
+    /// Adds the jamo to the composing syllable or commits it and starts a new one
+    fn type_jamo(&mut self, jamo: char) -> Vec<EngineAction> {
+        let syllable = self.syllable();
+        let next = if is_vowel(jamo) {
+            match syllable {
+                Syllable {
+                    jungseong: None, ..
+                } => Some(Syllable {
+                    jungseong: Some(jamo),
+                    ..syllable
+                }),
+                Syllable {
+                    jungseong: Some(jungseong),
+                    jongseong: None,
+                    ..
+                } => combine(&COMPOUND_VOWELS, jungseong, jamo).map(|jungseong| Syllable {
+                    jungseong: Some(jungseong),
+                    ..syllable
+                }),
+                Syllable {
+                    jongseong: Some(jongseong),
+                    ..
+                } => {
+                    // The final consonant becomes the initial consonant of the next syllable
+                    let (remaining, moved) = match split(jongseong) {
+                        Some((first, second)) => (Some(first), second),
+                        None => (None, jongseong),
+                    };
+                    let choseong = Syllable {
+                        choseong: Some(moved),
+                        ..Syllable::default()
+                    };
+                    let next = Syllable {
+                        jungseong: Some(jamo),
+                        ..choseong
+                    };
+                    let syllable = Syllable {
+                        jongseong: remaining,
+                        ..syllable
+                    };
+                    return self.commit(syllable, vec![choseong, next]);
+                }
+            }
+        } else {
+            match syllable {
+                Syllable {
+                    choseong: None,
+                    jungseong: None,
+                    ..
+                } => Some(Syllable {
+                    choseong: Some(jamo),
+                    ..syllable
+                }),
+                Syllable {
+                    choseong: Some(_),
+                    jungseong: Some(_),
+                    jongseong: None,
+                } if JONGSEONG.contains(&jamo) => Some(Syllable {
+                    jongseong: Some(jamo),
+                    ..syllable
+                }),
+                Syllable {
+                    jongseong: Some(jongseong),
+                    ..
+                } => combine(&COMPOUND_FINALS, jongseong, jamo).map(|jongseong| Syllable {
+                    jongseong: Some(jongseong),
+                    ..syllable
+                }),
+                _ => None,
+            }
+        };
+        match next {
+            Some(next) => {
+                self.states.push(next);
+                vec![self.preedit()]
+            }
+            None => {
+                let start = match is_vowel(jamo) {
+                    true => Syllable {
+                        jungseong: Some(jamo),
+                        ..Syllable::default()
+                    },
+                    false => Syllable {
+                        choseong: Some(jamo),
+                        ..Syllable::default()
+                    },
+                };
+                self.commit(syllable, vec![start])
+            }
+        }
+    }
+}
+
+impl InputEngine for HangulEngine {
+    fn process(&mut self, input: Input, _context: &EngineContext) -> Vec<EngineAction> {
+        let jamo = match &input {
+            Input::Char(c) => to_jamo(*c),
+            Input::Key(key) => LETTER_KEYS
+                .iter()
+                .find(|(code, _)| code == key)
+                .and_then(|(_, letter)| to_jamo(*letter)),
+            _ => None,
+        };
+        if let Some(jamo) = jamo {
+            return self.type_jamo(jamo);
+        }
+        if self.states.is_empty() {
+            return vec![EngineAction::Forward(input)];
+        }
+        match input {
+            Input::Backspace => {
+                self.states.pop();
+                vec![self.preedit()]
+            }
+            Input::Cancel => {
+                self.states.clear();
+                vec![self.preedit()]
+            }
+            Input::Confirm => self.commit(self.syllable(), Vec::new()),
+            // Any other input ends the syllable
+            input => {
+                let mut actions = self.commit(self.syllable(), Vec::new());
+                actions.push(EngineAction::Forward(input));
+                actions
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.states.clear();
+    }
+}
+
+/// Returns the jamo for a compatibility jamo or a letter of the 2-set layout
+fn to_jamo(c: char) -> Option<char> {
+    if CHOSEONG.contains(&c) || JUNGSEONG.contains(&c) {
+        return Some(c);
+    }
+    if let Some((_, jamo)) = SHIFTED_LAYOUT.iter().find(|(letter, _)| *letter == c) {
+        return Some(*jamo);
+    }
+    match c.to_ascii_lowercase() {
+        letter @ 'a'..='z' => Some(LAYOUT[letter as usize - 'a' as usize]),
+        _ => None,
+    }
+}
+
+/// Returns true if the jamo is a vowel
+fn is_vowel(jamo: char) -> bool {
+    JUNGSEONG.contains(&jamo)
+}
+
+/// Returns the jamo that is combined from the two jamo
+fn combine(table: &[(char, char, char)], first: char, second: char) -> Option<char> {
+    table
+        .iter()
+        .find(|(a, b, _)| *a == first && *b == second)
+        .map(|(_, _, combined)| *combined)
+}
+
+/// Returns the two consonants a compound final consonant is combined from
+fn split(jongseong: char) -> Option<(char, char)> {
+    COMPOUND_FINALS
+        .iter()
+        .find(|(_, _, combined)| *combined == jongseong)
+        .map(|(first, second, _)| (*first, *second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::type_inputs;
+
+    fn chars(text: &str) -> Vec<Input> {
+        text.chars().map(Input::Char).collect()
+    }
+
+    fn typed(text: &str) -> (String, String) {
+        type_inputs(&mut HangulEngine::new(), chars(text))
+    }
+
+    #[test]
+    fn jamo_are_composed_to_syllables() {
+        assert_eq!(typed("ㅎㅏㄴ"), (String::new(), "한".to_string()));
+        assert_eq!(typed("ㅎㅏㄴㄱㅜㄱ"), ("한".to_string(), "국".to_string()));
+        // Compound vowels and final consonants
+        assert_eq!(typed("ㄱㅗㅏ"), (String::new(), "과".to_string()));
+        assert_eq!(typed("ㄷㅏㄹㄱ"), (String::new(), "닭".to_string()));
+        // Letters of the 2-set layout
+        assert_eq!(typed("gksrmf"), ("한".to_string(), "글".to_string()));
+        // Jamo without an initial consonant are committed alone
+        assert_eq!(typed("ㅏㄱ"), ("ㅏ".to_string(), "ㄱ".to_string()));
+    }
+
+    #[test]
+    fn final_consonants_move_to_the_next_syllable() {
+        assert_eq!(typed("ㄱㅏㄴㅏ"), ("가".to_string(), "나".to_string()));
+        // Only the second consonant of a compound final consonant moves
+        assert_eq!(typed("ㄷㅏㄹㄱㅣ"), ("달".to_string(), "기".to_string()));
+        assert_eq!(typed("ㅇㅓㅂㅅㅓ"), ("업".to_string(), "서".to_string()));
+    }
+
+    #[test]
+    fn syllables_are_decomposed_one_jamo_at_a_time() {
+        let mut engine = HangulEngine::new();
+        assert_eq!(
+            type_inputs(&mut engine, chars("ㄷㅏㄹㄱ")),
+            (String::new(), "닭".to_string())
+        );
+        for preedit in ["달", "다", "ㄷ", ""] {
+            assert_eq!(
+                type_inputs(&mut engine, vec![Input::Backspace]),
+                (String::new(), preedit.to_string())
+            );
+        }
+        // Without a composing syllable, backspaces are forwarded
+        assert_eq!(
+            type_inputs(&mut engine, vec![Input::Backspace]),
+            ("Backspace".to_string(), String::new())
+        );
+        let mut engine = HangulEngine::new();
+        let mut inputs = chars("ㄱㅗㅏ");
+        inputs.push(Input::Backspace);
+        assert_eq!(
+            type_inputs(&mut engine, inputs),
+            (String::new(), "고".to_string())
+        );
+        // The moved consonant is a new syllable
+        let mut inputs = chars("ㄷㅏㄹㄱㅏ");
+        inputs.extend([Input::Backspace, Input::Backspace]);
+        assert_eq!(
+            type_inputs(&mut HangulEngine::new(), inputs),
+            ("달".to_string(), String::new())
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use super::Transaction;
+
+/// Maximum number of edits that can be undone
+const MAX_HISTORY_LEN: usize = 100;
+
+/// Change of the surrounding text made by a commit of the input method
+///
+/// Positions are in bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Edit {
+    /// Start of the changed range in the surrounding text
+    pub position: usize,
+    /// Text that was deleted
+    pub removed: String,
+    /// Text that was inserted in place of the deleted text
+    pub inserted: String,
+}
+
+impl Edit {
+    /// Returns the edit that reverts this edit
+    fn inverse(&self) -> Edit {
+        Edit {
+            position: self.position,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+
+    /// Returns the transaction that executes the edit on 'text' with the cursor at the byte offset 'cursor'
+    ///
+    /// Returns None if the text at the position is not the removed text anymore
+    /// or if the cursor is not within it, because the requests can only delete around the cursor
+    fn to_transaction(&self, text: &str, cursor: usize) -> Option<Transaction> {
+        let end = self.position + self.removed.len();
+        if text.get(self.position..end) != Some(self.removed.as_str())
+            || !(self.position..=end).contains(&cursor)
+        {
+            return None;
+        }
+        Some(
+            Transaction::new()
+                .delete_surrounding_text(cursor - self.position, end - cursor)
+                .commit_string(self.inserted.clone()),
+        )
+    }
+}
+
+/// Edits of the input method that can be undone and redone
+#[derive(Clone, Debug, Default)]
+pub(crate) struct History {
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+}
+
+impl History {
+    /// Remembers a new edit. The edits that were undone can not be redone anymore
+    pub(crate) fn record(&mut self, edit: Edit) {
+        if self.undo_stack.len() == MAX_HISTORY_LEN {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+    }
+
+    /// Forgets all edits, because the surrounding text was changed by something else than the input method
+    pub(crate) fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Returns the transaction that reverts the last edit
+    ///
+    /// If the last edit can not be reverted anymore, the history is cleared and None is returned
+    pub(crate) fn undo_transaction(&mut self, text: &str, cursor: usize) -> Option<Transaction> {
+        let edit = self.undo_stack.last()?;
+        let transaction = edit.inverse().to_transaction(text, cursor);
+        if transaction.is_none() {
+            self.clear();
+        }
+        transaction
+    }
+
+    /// Returns the transaction that repeats the last edit that was undone
+    ///
+    /// If the edit can not be repeated anymore, the history is cleared and None is returned
+    pub(crate) fn redo_transaction(&mut self, text: &str, cursor: usize) -> Option<Transaction> {
+        let edit = self.redo_stack.last()?;
+        let transaction = edit.to_transaction(text, cursor);
+        if transaction.is_none() {
+            self.clear();
+        }
+        transaction
+    }
+
+    /// Moves the last edit to the edits that can be redone, after its undo transaction was committed
+    pub(crate) fn undone(&mut self) {
+        if let Some(edit) = self.undo_stack.pop() {
+            self.redo_stack.push(edit);
+        }
+    }
+
+    /// Moves the last undone edit back to the edits that can be undone, after its transaction was committed
+    pub(crate) fn redone(&mut self) {
+        if let Some(edit) = self.redo_stack.pop() {
+            self.undo_stack.push(edit);
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use super::capitalization::{capitalized, is_capitalized, is_uppercase};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How the flags of the words and affixes are written in a Hunspell dictionary
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FlagType {
+    /// Each char is a flag
+    Char,
+    /// Each pair of chars is a flag
+    Long,
+    /// Flags are decimal numbers separated by commas
+    Numeric,
+}
+
+/// Class of chars in the condition of an affix
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CharClass {
+    Any,
+    Char(char),
+    Set { chars: Vec<char>, negated: bool },
+}
+
+impl CharClass {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Any => true,
+            CharClass::Char(expected) => c == *expected,
+            CharClass::Set { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+/// Rule of a prefix or suffix
+#[derive(Clone, Debug)]
+struct AffixRule {
+    strip: String,
+    add: String,
+    // Classes of the chars at the start of the word for prefixes or at the end for suffixes
+    condition: Vec<CharClass>,
+}
+
+impl AffixRule {
+    /// Applies the rule to the word if it matches the condition
+    fn apply(&self, word: &str, prefix: bool) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        if self.condition.len() > chars.len() {
+            return None;
+        }
+        let checked = match prefix {
+            true => &chars[..self.condition.len()],
+            false => &chars[chars.len() - self.condition.len()..],
+        };
+        if !self
+            .condition
+            .iter()
+            .zip(checked)
+            .all(|(class, c)| class.matches(*c))
+        {
+            return None;
+        }
+        match prefix {
+            true => word
+                .strip_prefix(&self.strip)
+                .map(|stem| self.add.clone() + stem),
+            false => word
+                .strip_suffix(&self.strip)
+                .map(|stem| stem.to_string() + &self.add),
+        }
+        // The whole word must not be stripped
+        .filter(|form| !form.is_empty())
+    }
+}
+
+/// Prefix or suffix with all its rules
+#[derive(Clone, Debug, Default)]
+struct Affix {
+    prefix: bool,
+    // True if the affix can be combined with an affix of the other kind
+    cross_product: bool,
+    rules: Vec<AffixRule>,
+}
+
+/// Word list of a Hunspell dictionary with all forms of the words
+///
+/// The prefixes and suffixes of the .aff file are applied to the words of the .dic file when it is loaded.
+/// Continuation classes of affixes and compounding are not supported
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Dictionary {
+    words: HashSet<String>,
+    // Correct words that are never suggested
+    no_suggest: HashSet<String>,
+    /// Chars to try for corrections, the most common first
+    pub try_chars: Vec<char>,
+    /// Common misspellings and their replacements
+    pub replacements: Vec<(String, String)>,
+    /// Rows of the keyboard, to find typos with neighbouring keys
+    pub keyboard: Vec<Vec<char>>,
+}
+
+/// Settings of the .aff file
+#[derive(Default)]
+struct AffixFile {
+    flag_type: Option<FlagType>,
+    affixes: HashMap<String, Affix>,
+    forbidden_flag: Option<String>,
+    need_affix_flag: Option<String>,
+    no_suggest_flag: Option<String>,
+}
+
+impl Dictionary {
+    /// Adds the words of the Hunspell dictionary with the .aff file at 'aff_path' and the .dic file at 'dic_path'
+    pub fn load<A: AsRef<Path>, D: AsRef<Path>>(
+        &mut self,
+        aff_path: A,
+        dic_path: D,
+    ) -> io::Result<()> {
+        let aff = fs::read(aff_path)?;
+        let dic = fs::read(dic_path)?;
+        let latin1 = String::from_utf8_lossy(&aff).lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some("SET")
+                && fields
+                    .next()
+                    .is_some_and(|encoding| encoding.eq_ignore_ascii_case("ISO8859-1"))
+        });
+        let affix_file = self.parse_aff(&decode(&aff, latin1));
+        self.parse_dic(&decode(&dic, latin1), &affix_file);
+        Ok(())
+    }
+
+    /// Adds the word as a correct word
+    pub fn add_word(&mut self, word: String) {
+        self.words.insert(word);
+    }
+
+    /// Returns true if the word is correct
+    ///
+    /// Capitalized and uppercase words are correct if their lowercase form is
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word)
+            || (is_capitalized(word) || is_uppercase(word))
+                && self.words.contains(&word.to_lowercase())
+            || is_uppercase(word) && self.words.contains(&capitalized(&word.to_lowercase()))
+    }
+
+    /// Returns true if the word is correct and can be suggested
+    pub fn can_suggest(&self, word: &str) -> bool {
+        self.words.contains(word) && !self.no_suggest.contains(word)
+    }
+
+    /// Reads the settings and affixes and keeps the settings needed for corrections
+    fn parse_aff(&mut self, content: &str) -> AffixFile {
+        let mut affix_file = AffixFile::default();
+        // Number of rules that follow the last affix header
+        let mut remaining_rules = 0;
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", "long", ..] => affix_file.flag_type = Some(FlagType::Long),
+                ["FLAG", "num", ..] => affix_file.flag_type = Some(FlagType::Numeric),
+                ["FLAG", ..] => affix_file.flag_type = Some(FlagType::Char),
+                ["TRY", chars, ..] => self.try_chars = chars.chars().collect(),
+                ["KEY", rows, ..] => {
+                    self.keyboard = rows.split('|').map(|row| row.chars().collect()).collect()
+                }
+                ["REP", from, to, ..] => self.replacements.push((
+                    from.trim_start_matches('^')
+                        .trim_end_matches('$')
+                        .replace('_', " "),
+                    to.trim_start_matches('^')
+                        .trim_end_matches('$')
+                        .replace('_', " "),
+                )),
+                ["FORBIDDENWORD", flag, ..] => affix_file.forbidden_flag = Some(flag.to_string()),
+                ["NEEDAFFIX", flag, ..] => affix_file.need_affix_flag = Some(flag.to_string()),
+                ["NOSUGGEST", flag, ..] => affix_file.no_suggest_flag = Some(flag.to_string()),
+                // A rule like 'SFX A y ies [^aeiou]y'
+                ["PFX" | "SFX", flag, strip, add, rest @ ..] if remaining_rules > 0 => {
+                    remaining_rules -= 1;
+                    let affix = match affix_file.affixes.get_mut(*flag) {
+                        Some(affix) => affix,
+                        None => continue,
+                    };
+                    let empty = |field: &str| match field {
+                        "0" => String::new(),
+                        field => field.to_string(),
+                    };
+                    // Continuation classes after a '/' are ignored
+                    let add = add.split('/').next().unwrap_or_default();
+                    affix.rules.push(AffixRule {
+                        strip: empty(strip),
+                        add: empty(add),
+                        condition: parse_condition(rest.first().copied().unwrap_or(".")),
+                    });
+                }
+                // The header of an affix like 'SFX A Y 3', followed by its rules
+                [kind @ ("PFX" | "SFX"), flag, cross_product, count, ..] => {
+                    remaining_rules = count.parse().unwrap_or(0);
+                    affix_file.affixes.insert(
+                        flag.to_string(),
+                        Affix {
+                            prefix: *kind == "PFX",
+                            cross_product: *cross_product == "Y",
+                            rules: Vec::new(),
+                        },
+                    );
+                }
+                _ => (),
+            }
+        }
+        affix_file
+    }
+
+    /// Adds the words and their forms with affixes
+    fn parse_dic(&mut self, content: &str, affix_file: &AffixFile) {
+        let flag_type = affix_file.flag_type.unwrap_or(FlagType::Char);
+        // The first line contains the approximate number of words
+        for line in content.lines().skip(1) {
+            // Morphological fields follow after whitespace
+            let entry = match line.split_whitespace().next() {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let (word, flags) = match entry.split_once('/') {
+                Some((word, flags)) => (word, parse_flags(flags, flag_type)),
+                None => (entry, Vec::new()),
+            };
+            let has_flag =
+                |flag: &Option<String>| flag.as_ref().is_some_and(|flag| flags.contains(flag));
+            if has_flag(&affix_file.forbidden_flag) {
+                self.words.remove(word);
+                continue;
+            }
+            let mut forms = expand(word, &flags, &affix_file.affixes);
+            if !has_flag(&affix_file.need_affix_flag) {
+                forms.push(word.to_string());
+            }
+            if has_flag(&affix_file.no_suggest_flag) {
+                self.no_suggest.extend(forms.iter().cloned());
+            }
+            self.words.extend(forms);
+        }
+    }
+}
+
+/// Returns the forms of the word with the affixes of the flags
+fn expand(word: &str, flags: &[String], affixes: &HashMap<String, Affix>) -> Vec<String> {
+    let affixes: Vec<&Affix> = flags.iter().filter_map(|flag| affixes.get(flag)).collect();
+    let mut forms = Vec::new();
+    for suffix in affixes.iter().filter(|affix| !affix.prefix) {
+        for form in suffix
+            .rules
+            .iter()
+            .filter_map(|rule| rule.apply(word, false))
+        {
+            // Prefixes are added to suffixed forms if both allow it
+            if suffix.cross_product {
+                for prefix in affixes
+                    .iter()
+                    .filter(|affix| affix.prefix && affix.cross_product)
+                {
+                    forms.extend(
+                        prefix
+                            .rules
+                            .iter()
+                            .filter_map(|rule| rule.apply(&form, true)),
+                    );
+                }
+            }
+            forms.push(form);
+        }
+    }
+    for prefix in affixes.iter().filter(|affix| affix.prefix) {
+        forms.extend(
+            prefix
+                .rules
+                .iter()
+                .filter_map(|rule| rule.apply(word, true)),
+        );
+    }
+    forms
+}
+
+/// Splits the flags of a word
+fn parse_flags(flags: &str, flag_type: FlagType) -> Vec<String> {
+    match flag_type {
+        FlagType::Char => flags.chars().map(String::from).collect(),
+        FlagType::Long => {
+            let chars: Vec<char> = flags.chars().collect();
+            chars.chunks(2).map(|flag| flag.iter().collect()).collect()
+        }
+        FlagType::Numeric => flags.split(',').map(str::to_string).collect(),
+    }
+}
+
+/// Parses a condition like '[^aeiou]y'
+fn parse_condition(condition: &str) -> Vec<CharClass> {
+    let mut classes = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        classes.push(match c {
+            '.' => CharClass::Any,
+            '[' => {
+                let mut set: Vec<char> = chars.by_ref().take_while(|c| *c != ']').collect();
+                let negated = set.first() == Some(&'^');
+                if negated {
+                    set.remove(0);
+                }
+                CharClass::Set {
+                    chars: set,
+                    negated,
+                }
+            }
+            c => CharClass::Char(c),
+        });
+    }
+    classes
+}
+
+/// Decodes a file, which is UTF-8 unless 'latin1' is set
+fn decode(bytes: &[u8], latin1: bool) -> String {
+    match latin1 {
+        true => bytes.iter().map(|byte| char::from(*byte)).collect(),
+        false => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(aff: &str, dic: &str) -> Dictionary {
+        let mut dictionary = Dictionary::default();
+        let affix_file = dictionary.parse_aff(aff);
+        dictionary.parse_dic(dic, &affix_file);
+        dictionary
+    }
+
+    #[test]
+    fn affixes_are_applied_with_strip_and_condition() {
+        let dictionary = dictionary(
+            "\
+SFX S Y 2
+SFX S y ies [^aeiou]y
+SFX S 0 s [aeiou]y
+PFX U Y 1
+PFX U 0 un .
+PFX R N 1
+PFX R 0 re .
+",
+            "3\ntry/SU\nplay/S\nfly/SR\n",
+        );
+        for word in [
+            "try", "tries", "untry", "untries", "plays", "flies", "refly",
+        ] {
+            assert!(dictionary.contains(word), "{}", word);
+        }
+        // The conditions do not match or the prefix is not a cross product
+        for word in ["trys", "plaies", "unplay", "reflies"] {
+            assert!(!dictionary.contains(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn rules_with_four_fields_are_not_headers() {
+        let dictionary = dictionary("SFX D Y 2\nSFX D e 0\nSFX D 0 1\n", "1\nmake/D\n");
+        assert!(dictionary.contains("mak"));
+        assert!(dictionary.contains("make1"));
+        assert_eq!(dictionary.words.len(), 3);
+    }
+
+    #[test]
+    fn long_flags_are_pairs_of_chars() {
+        let dictionary = dictionary(
+            "FLAG long\nSFX Aa Y 1\nSFX Aa 0 s .\nPFX Bb Y 1\nPFX Bb 0 re .\n",
+            "1\nwork/AaBb\n",
+        );
+        for word in ["work", "works", "rework", "reworks"] {
+            assert!(dictionary.contains(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn numeric_flags_and_special_flags_are_applied() {
+        let dictionary = dictionary(
+            "\
+FLAG num
+FORBIDDENWORD 7
+NEEDAFFIX 8
+NOSUGGEST 9
+SFX 101 Y 1
+SFX 101 0 ed .
+",
+            "4\nwalk/101\nwalked/7\nbake/8,101\ndarn/9\n",
+        );
+        assert!(dictionary.contains("walk"));
+        assert!(!dictionary.contains("walked"));
+        assert!(dictionary.contains("bakeed"));
+        assert!(!dictionary.contains("bake"));
+        assert!(dictionary.contains("darn"));
+        assert!(!dictionary.can_suggest("darn"));
+        assert!(dictionary.can_suggest("walk"));
+    }
+
+    #[test]
+    fn capitalized_forms_of_words_are_correct() {
+        let dictionary = dictionary("", "2\nhello\nParis\n");
+        for word in ["hello", "Hello", "HELLO", "Paris", "PARIS"] {
+            assert!(dictionary.contains(word), "{}", word);
+        }
+        for word in ["hELLO", "paris", "pARIS"] {
+            assert!(!dictionary.contains(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn settings_for_corrections_are_read() {
+        let dictionary = dictionary("TRY esia\nKEY qwe|asd\nREP ^alot$ a_lot\n", "0\n");
+        assert_eq!(dictionary.try_chars, vec!['e', 's', 'i', 'a']);
+        assert_eq!(
+            dictionary.keyboard,
+            vec![vec!['q', 'w', 'e'], vec!['a', 's', 'd']]
+        );
+        assert_eq!(
+            dictionary.replacements,
+            vec![("alot".to_string(), "a lot".to_string())]
+        );
+    }
+}
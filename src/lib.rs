@@ -9,18 +9,121 @@ extern crate log;
 use std::sync::{Arc, Mutex};
 use wayland_client::{protocol::wl_seat::WlSeat, Main};
 use wayland_protocols::misc::zwp_input_method_v2::client::zwp_input_method_manager_v2::ZwpInputMethodManagerV2;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
 
 mod traits;
 pub use traits::*;
 
+mod delta;
+pub use delta::{Divergence, TextDelta};
+
+mod transaction;
+pub use transaction::{Preedit, Transaction};
+
+mod editing;
+pub use editing::EditCommand;
+
+mod context;
+pub use context::{Segment, TextContext, MAX_SURROUNDING_TEXT_LEN};
+
+mod capitalization;
+pub use capitalization::ShiftState;
+
+mod filter;
+pub use filter::FilterMode;
+
+mod return_key;
+pub use return_key::{ReturnKeyAction, KEY_ENTER};
+
+mod sensitive;
+pub use sensitive::is_sensitive;
+
+mod protocol;
+pub use protocol::{Event, Request};
+
+mod validation;
+pub use validation::{ProtocolViolation, ValidationMode};
+
+mod history;
+
+mod engine;
+pub use engine::{EngineAction, EngineContext, Input, InputEngine, Pipeline};
+
+mod compose;
+pub use compose::{ComposeEngine, KEY_COMPOSE};
+
+mod codepoint;
+pub use codepoint::CodePointEngine;
+
+mod transliteration;
+pub use transliteration::TransliterationEngine;
+
+mod hangul;
+pub use hangul::HangulEngine;
+
+mod pinyin;
+pub use pinyin::PinyinEngine;
+
+mod prediction;
+pub use prediction::PredictionEngine;
+
+mod hunspell;
+
+mod autocorrect;
+pub use autocorrect::AutocorrectEngine;
+
+#[cfg(feature = "emoji")]
+mod emoji;
+#[cfg(feature = "emoji")]
+pub use emoji::{EmojiEngine, SkinTone};
+
+#[cfg(feature = "snippets")]
+mod snippets;
+#[cfg(feature = "snippets")]
+pub use snippets::SnippetEngine;
+
+#[cfg(feature = "record")]
+mod recording;
+#[cfg(feature = "record")]
+pub use recording::{Record, Replayer};
+
 use arc_input_method::*;
 mod arc_input_method;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Error when sending a request to the wayland-client
 pub enum SubmitError {
     /// Input method was not activ
     NotActive,
+    /// The range to delete exceeds the surrounding text or splits a char
+    InvalidDeleteRange,
+    /// The cursor of the preedit string is outside of the preedit string or splits a char
+    InvalidPreeditCursor,
+    /// The text contains a char that is not valid for the content purpose
+    InvalidInput {
+        purpose: ContentPurpose,
+        character: char,
+    },
+    /// The request violates the rules of the protocol. This is only reported with ValidationMode::Error
+    ProtocolViolation(ProtocolViolation),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Decides how changes of the surrounding text are reported, that only mirror the input method's own edits
+///
+/// The surrounding text is updated as soon as the input method commits a change, so this notification is the echo.
+/// When the wayland server sends the resulting surrounding text back, it is only reported if it differs from the
+/// text that was expected after the commit
+pub enum EchoMode {
+    /// Echoes are reported like any other change (default)
+    #[default]
+    Notify,
+    /// Echoes are reported, but the TextDelta is marked as an echo
+    Tag,
+    /// Echoes are not reported to the content connector
+    Suppress,
 }
 
 #[derive(Clone, Debug)]
@@ -65,3 +168,160 @@ impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> InputMethod<T, D>
         self.im_service_arc.lock().unwrap().get_surrounding_text()
     }
 }
+
+impl<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText> IMService<T, D> {
+    /// Creates a new IMService, that is not connected to a wayland server
+    ///
+    /// Requests are not sent anywhere, but they still change the state of the IMService. Events can be passed to
+    /// handle_event(). This allows replaying recorded sessions and testing input methods without a wayland server
+    pub fn new_detached(ui_connector: T, content_connector: D) -> Self {
+        let im_service_arc = IMServiceArc::new_detached(ui_connector, content_connector);
+        IMService { im_service_arc }
+    }
+
+    /// Handles the event like it was sent by the wayland server
+    ///
+    /// This only works for IMServices created with new_detached(). Events passed to an IMService that is connected
+    /// to a wayland server are ignored
+    pub fn handle_event(&self, event: Event) {
+        self.im_service_arc.lock().unwrap().handle_event(event)
+    }
+
+    /// Starts writing every event and request to the file at 'path' with one JSON object per line
+    ///
+    /// An existing file is overwritten. Texts of sensitive text inputs are masked.
+    /// The file can be read by a Replayer to reproduce the session
+    #[cfg(feature = "record")]
+    pub fn start_recording<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        self.im_service_arc
+            .lock()
+            .unwrap()
+            .start_recording(path.as_ref())
+    }
+
+    /// Stops writing events and requests to the file
+    #[cfg(feature = "record")]
+    pub fn stop_recording(&self) {
+        self.im_service_arc.lock().unwrap().stop_recording()
+    }
+
+    /// Sends a 'set_preedit_string' request to the wayland server
+    ///
+    /// The preedit string is shown with the next commit
+    pub fn set_preedit_string(&self, preedit: Preedit) -> Result<(), SubmitError> {
+        self.im_service_arc
+            .lock()
+            .unwrap()
+            .set_preedit_string(preedit)
+    }
+
+    /// Sends all requests of the transaction and commits them
+    ///
+    /// The requests are applied in the order the wayland server applies them: delete, insert and then preedit
+    pub fn submit(&self, transaction: Transaction) -> Result<(), SubmitError> {
+        self.im_service_arc.lock().unwrap().submit(transaction)
+    }
+
+    /// Reverts the last edit of the input method, for example the last committed word or autocorrection
+    ///
+    /// Returns Ok(false) if there is nothing to undo. The history is cleared if the application changes the text
+    /// or another text input is activated
+    pub fn undo(&self) -> Result<bool, SubmitError> {
+        self.im_service_arc.lock().unwrap().undo()
+    }
+
+    /// Repeats the last edit that was reverted with undo()
+    ///
+    /// Returns Ok(false) if there is nothing to redo
+    pub fn redo(&self) -> Result<bool, SubmitError> {
+        self.im_service_arc.lock().unwrap().redo()
+    }
+
+    /// Executes the editing command, like deleting the previous word, on the surrounding text
+    ///
+    /// The resulting requests are committed right away
+    pub fn edit(&self, command: &EditCommand) -> Result<(), SubmitError> {
+        self.im_service_arc.lock().unwrap().edit(command)
+    }
+
+    /// Returns the words and sentence information around the cursor
+    ///
+    /// At most 'previous_word_count' words before the current word are returned
+    pub fn text_context(&self, previous_word_count: usize) -> TextContext {
+        self.im_service_arc
+            .lock()
+            .unwrap()
+            .text_context(previous_word_count)
+    }
+
+    /// Enables or disables the shift state advice
+    ///
+    /// If enabled, the ui_connector is told after every change whether the next letter should be capitalized.
+    /// The advice is based on the content hint and the sentence around the cursor
+    pub fn set_auto_capitalization(&self, enabled: bool) {
+        self.im_service_arc
+            .lock()
+            .unwrap()
+            .set_auto_capitalization(enabled)
+    }
+
+    /// Sets how committed text is checked against the content purpose, for example to reject letters if only digits are allowed
+    ///
+    /// The filter applies to commit_string() and to the commit string of submitted transactions
+    pub fn set_filter_mode(&self, filter_mode: FilterMode) {
+        self.im_service_arc
+            .lock()
+            .unwrap()
+            .set_filter_mode(filter_mode)
+    }
+
+    /// Returns what the return key should do in the current text input, for example to label it
+    pub fn return_key_action(&self) -> ReturnKeyAction {
+        self.im_service_arc.lock().unwrap().return_key_action()
+    }
+
+    /// Handles a press of the return key
+    ///
+    /// A line break is committed to multiline text inputs. For all other text inputs 'send_key' is called with
+    /// the keycode of the enter key, so it can be sent for example with a virtual keyboard
+    ///
+    /// 'send_key' is called while the IMService is locked, so it must not call any methods of the IMService
+    pub fn press_return<F: FnOnce(u32)>(
+        &self,
+        send_key: F,
+    ) -> Result<ReturnKeyAction, SubmitError> {
+        self.im_service_arc.lock().unwrap().press_return(send_key)
+    }
+
+    /// Returns the content hint and purpose of the current text input
+    pub fn content_type(&self) -> (ContentHint, ContentPurpose) {
+        self.im_service_arc.lock().unwrap().content_type()
+    }
+
+    /// Returns if the current text input contains sensitive data like passwords
+    ///
+    /// While this is set, text is redacted in the logs and engines like learning dictionaries should not store any text
+    pub fn is_sensitive(&self) -> bool {
+        self.im_service_arc.lock().unwrap().is_sensitive()
+    }
+
+    /// Sets how requests are checked against the rules of the input-method-v2 protocol before they are sent
+    ///
+    /// This helps during development, because the wayland server silently ignores invalid requests or disconnects the input method
+    pub fn set_validation_mode(&self, validation_mode: ValidationMode) {
+        self.im_service_arc
+            .lock()
+            .unwrap()
+            .set_validation_mode(validation_mode)
+    }
+
+    /// Returns the preedit string that was shown with the last commit
+    pub fn get_preedit(&self) -> Preedit {
+        self.im_service_arc.lock().unwrap().get_preedit()
+    }
+
+    /// Sets how changes of the surrounding text are reported, that only mirror the input method's own commits
+    pub fn set_echo_mode(&self, echo_mode: EchoMode) {
+        self.im_service_arc.lock().unwrap().set_echo_mode(echo_mode)
+    }
+}
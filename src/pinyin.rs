@@ -0,0 +1,385 @@
+use super::{EngineAction, EngineContext, Input, InputEngine};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Linux input event code of the key to show the previous page of candidates
+const KEY_PAGEUP: u32 = input_event_codes::KEY_PAGEUP!();
+
+/// Linux input event code of the key to show the next page of candidates
+const KEY_PAGEDOWN: u32 = input_event_codes::KEY_PAGEDOWN!();
+
+/// Separator of the syllables in the keys of the dictionary and in the preedit string
+const SEPARATOR: char = '\'';
+
+/// Engine that converts pinyin to Chinese phrases of a dictionary
+///
+/// Typed lowercase letters are split into pinyin syllables and shown as the preedit string like "ni'hao".
+/// A 'v' is typed for 'ü' and an apostrophe separates syllables explicitly. The phrases for the syllables are offered
+/// as candidates, phrases for more syllables and with a higher frequency first. If a phrase only covers the first
+/// syllables, the remaining syllables stay in the preedit string after it was selected.
+///
+/// The candidates are split into pages, which are shown with IMVisibility::show_candidates() when the engine runs in a
+/// Pipeline. Input::Select or a digit selects a candidate of the current page and a space selects the first one.
+/// If there are no candidates, a space commits the typed letters followed by the space. Input::Key with KEY_PAGEDOWN or '=' shows the next page and KEY_PAGEUP or '-' the previous one.
+/// Input::Confirm commits the typed letters unchanged. The engine is disabled in text inputs for sensitive data
+#[derive(Clone, Debug)]
+pub struct PinyinEngine {
+    // Phrases with their frequency for the syllables joined by the separator
+    dictionary: BTreeMap<String, Vec<(String, u64)>>,
+    syllables: HashSet<String>,
+    // Length of the longest syllable in bytes
+    max_syllable_len: usize,
+    page_size: usize,
+    // Typed letters and separators
+    typed: String,
+    // All candidates with the number of syllables they cover
+    candidates: Vec<(String, usize)>,
+    page: usize,
+}
+
+impl Default for PinyinEngine {
+    fn default() -> Self {
+        PinyinEngine {
+            dictionary: BTreeMap::new(),
+            syllables: HashSet::new(),
+            max_syllable_len: 0,
+            page_size: 5,
+            typed: String::new(),
+            candidates: Vec::new(),
+            page: 0,
+        }
+    }
+}
+
+impl PinyinEngine {
+    /// Creates a new PinyinEngine without any phrases, that shows 5 candidates per page
+    pub fn new() -> Self {
+        PinyinEngine::default()
+    }
+
+    /// Creates a new PinyinEngine with the phrases of the dictionary file at 'path'
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut engine = PinyinEngine::new();
+        engine.load_file(path)?;
+        Ok(engine)
+    }
+
+    /// Sets the number of candidates per page, which has to be between 1 and 9 to select them with a digit
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.clamp(1, 9);
+        self
+    }
+
+    /// Adds the phrases of the dictionary file at 'path'
+    ///
+    /// Each line contains a phrase, its syllables separated by spaces or apostrophes and optionally its frequency:
+    ///
+    /// 你好 ni hao 1000
+    ///
+    /// Lines starting with '#' are ignored
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        for line in content.lines() {
+            let mut fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 2 || fields[0].starts_with('#') {
+                continue;
+            }
+            let frequency = match fields.last().and_then(|field| field.parse().ok()) {
+                Some(frequency) if fields.len() > 2 => {
+                    fields.pop();
+                    frequency
+                }
+                _ => 0,
+            };
+            let syllables: Vec<&str> = fields[1..]
+                .iter()
+                .flat_map(|field| field.split(SEPARATOR))
+                .filter(|syllable| !syllable.is_empty())
+                .collect();
+            self.add_phrase(fields[0].to_string(), &syllables, frequency);
+        }
+        Ok(())
+    }
+
+    /// Adds a phrase with the pinyin syllables it is typed with. 'ü' is written as 'v'
+    pub fn add_phrase(&mut self, phrase: String, syllables: &[&str], frequency: u64) {
+        if syllables.is_empty() {
+            return;
+        }
+        let syllables: Vec<String> = syllables
+            .iter()
+            .map(|syllable| syllable.to_lowercase().replace('ü', "v"))
+            .collect();
+        for syllable in &syllables {
+            self.max_syllable_len = self.max_syllable_len.max(syllable.len());
+            self.syllables.insert(syllable.clone());
+        }
+        let phrases = self
+            .dictionary
+            .entry(syllables.join(&SEPARATOR.to_string()))
+            .or_default();
+        match phrases.iter_mut().find(|(known, _)| *known == phrase) {
+            Some(entry) => entry.1 = entry.1.max(frequency),
+            None => phrases.push((phrase, frequency)),
+        }
+    }
+
+    /// Splits the typed letters into syllables with their end in the typed text
+    ///
+    /// The longest known syllable is taken first. The last syllable can be incomplete
+    fn segments(&self) -> Vec<(&str, usize)> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while start < self.typed.len() {
+            let rest = &self.typed[start..];
+            if rest.starts_with(SEPARATOR) {
+                start += 1;
+                continue;
+            }
+            let word_len = rest.find(SEPARATOR).unwrap_or(rest.len());
+            let length = (1..=word_len.min(self.max_syllable_len))
+                .rev()
+                .find(|length| self.syllables.contains(&rest[..*length]))
+                // An unknown syllable takes all letters until the next separator
+                .unwrap_or(word_len);
+            segments.push((&rest[..length], start + length));
+            start += length;
+        }
+        segments
+    }
+
+    /// Looks up the candidates for the typed syllables
+    fn find_candidates(&self) -> Vec<(String, usize)> {
+        let segments = self.segments();
+        let mut candidates = Vec::new();
+        for count in (1..=segments.len()).rev() {
+            let key = segments[..count]
+                .iter()
+                .map(|(syllable, _)| *syllable)
+                .collect::<Vec<_>>()
+                .join(&SEPARATOR.to_string());
+            let mut phrases: Vec<(bool, &(String, u64))> = match count == segments.len() {
+                // The last typed syllable can be the beginning of a syllable
+                true => self
+                    .dictionary
+                    .range(key.clone()..)
+                    .take_while(|(known, _)| known.starts_with(&key))
+                    .filter(|(known, _)| known.matches(SEPARATOR).count() == count - 1)
+                    .flat_map(|(known, phrases)| {
+                        let exact = *known == key;
+                        phrases.iter().map(move |phrase| (exact, phrase))
+                    })
+                    .collect(),
+                false => self
+                    .dictionary
+                    .get(&key)
+                    .into_iter()
+                    .flatten()
+                    .map(|phrase| (true, phrase))
+                    .collect(),
+            };
+            // Complete syllables first, then by frequency
+            phrases.sort_by(|(exact_a, (_, a)), (exact_b, (_, b))| {
+                exact_b.cmp(exact_a).then(b.cmp(a))
+            });
+            for (_, (phrase, _)) in phrases {
+                if !candidates.iter().any(|(known, _)| known == phrase) {
+                    candidates.push((phrase.clone(), count));
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Returns the candidates of the current page
+    fn page(&self) -> Vec<String> {
+        self.candidates
+            .iter()
+            .skip(self.page * self.page_size)
+            .take(self.page_size)
+            .map(|(phrase, _)| phrase.clone())
+            .collect()
+    }
+
+    /// Returns the actions to show the typed syllables and the candidates for them
+    fn update(&mut self) -> Vec<EngineAction> {
+        self.candidates = self.find_candidates();
+        self.page = 0;
+        let preedit = self
+            .segments()
+            .iter()
+            .map(|(syllable, _)| *syllable)
+            .collect::<Vec<_>>()
+            .join(&SEPARATOR.to_string());
+        vec![
+            EngineAction::Preedit(preedit),
+            EngineAction::Candidates(self.page()),
+        ]
+    }
+
+    /// Moves to another page of candidates if it exists
+    fn turn_page(&mut self, forward: bool) -> Vec<EngineAction> {
+        let pages = self.candidates.len().div_ceil(self.page_size);
+        match forward {
+            true if self.page + 1 < pages => self.page += 1,
+            false if self.page > 0 => self.page -= 1,
+            _ => return Vec::new(),
+        }
+        vec![EngineAction::Candidates(self.page())]
+    }
+
+    /// Commits the candidate at the index of the current page and keeps the syllables it does not cover
+    fn select(&mut self, index: usize) -> Vec<EngineAction> {
+        if index >= self.page_size {
+            return Vec::new();
+        }
+        let (phrase, count) = match self.candidates.get(self.page * self.page_size + index) {
+            Some(candidate) => candidate.clone(),
+            None => return Vec::new(),
+        };
+        let end = self.segments()[count - 1].1;
+        self.typed = self.typed[end..].trim_start_matches(SEPARATOR).to_string();
+        let mut actions = vec![EngineAction::Commit(phrase)];
+        actions.append(&mut self.update());
+        actions
+    }
+
+    /// Discards the typed letters and returns the actions to remove the preedit string and the candidates
+    fn clear(&mut self) -> Vec<EngineAction> {
+        self.reset();
+        vec![
+            EngineAction::Preedit(String::new()),
+            EngineAction::Candidates(Vec::new()),
+        ]
+    }
+}
+
+impl InputEngine for PinyinEngine {
+    fn process(&mut self, input: Input, context: &EngineContext) -> Vec<EngineAction> {
+        if context.sensitive {
+            return vec![EngineAction::Forward(input)];
+        }
+        if self.typed.is_empty() {
+            return match input {
+                Input::Char(c @ 'a'..='z') => {
+                    self.typed.push(c);
+                    self.update()
+                }
+                input => vec![EngineAction::Forward(input)],
+            };
+        }
+        match input {
+            Input::Char(c @ 'a'..='z') => {
+                self.typed.push(c);
+                self.update()
+            }
+            Input::Char(SEPARATOR) if !self.typed.ends_with(SEPARATOR) => {
+                self.typed.push(SEPARATOR);
+                self.update()
+            }
+            Input::Char(' ') => match self.candidates.is_empty() {
+                true => {
+                    let typed = std::mem::take(&mut self.typed);
+                    let mut actions = self.clear();
+                    actions.push(EngineAction::Commit(typed + " "));
+                    actions
+                }
+                false => self.select(0),
+            },
+            Input::Char(digit @ '1'..='9') => self.select(digit as usize - '1' as usize),
+            Input::Select(index) => self.select(index),
+            Input::Char('=') | Input::Key(KEY_PAGEDOWN) => self.turn_page(true),
+            Input::Char('-') | Input::Key(KEY_PAGEUP) => self.turn_page(false),
+            Input::Backspace => {
+                self.typed.pop();
+                match self.typed.is_empty() {
+                    true => self.clear(),
+                    false => self.update(),
+                }
+            }
+            Input::Confirm => {
+                let typed = std::mem::take(&mut self.typed);
+                let mut actions = self.clear();
+                actions.push(EngineAction::Commit(typed));
+                actions
+            }
+            Input::Cancel => self.clear(),
+            // Other chars are ignored while syllables are typed
+            Input::Char(_) => Vec::new(),
+            input => {
+                let mut actions = self.clear();
+                actions.push(EngineAction::Forward(input));
+                actions
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.typed.clear();
+        self.candidates.clear();
+        self.page = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(typed: &str) -> PinyinEngine {
+        let mut engine = PinyinEngine::new();
+        engine.add_phrase("先".to_string(), &["xian"], 100);
+        engine.add_phrase("现".to_string(), &["xian"], 300);
+        engine.add_phrase("想".to_string(), &["xiang"], 1000);
+        engine.add_phrase("西安".to_string(), &["xi", "an"], 200);
+        engine.add_phrase("西".to_string(), &["xi"], 10);
+        engine.add_phrase("安".to_string(), &["an"], 10);
+        engine.typed = typed.to_string();
+        engine
+    }
+
+    fn syllables(engine: &PinyinEngine) -> Vec<&str> {
+        engine
+            .segments()
+            .into_iter()
+            .map(|(syllable, _)| syllable)
+            .collect()
+    }
+
+    #[test]
+    fn longest_syllables_are_taken_first() {
+        assert_eq!(syllables(&engine("xian")), vec!["xian"]);
+        assert_eq!(syllables(&engine("xi'an")), vec!["xi", "an"]);
+        assert_eq!(syllables(&engine("xianxi")), vec!["xian", "xi"]);
+        // Unknown letters are kept until the next separator
+        assert_eq!(syllables(&engine("xianz")), vec!["xian", "z"]);
+        assert_eq!(syllables(&engine("qq'an")), vec!["qq", "an"]);
+        assert_eq!(engine("xi'an").segments(), vec![("xi", 2), ("an", 5)]);
+    }
+
+    #[test]
+    fn candidates_are_ordered_by_syllables_and_frequency() {
+        let phrases = |typed: &str| -> Vec<(String, usize)> { engine(typed).find_candidates() };
+        // Complete syllables come before more frequent beginnings of syllables
+        assert_eq!(
+            phrases("xian"),
+            vec![
+                ("现".to_string(), 1),
+                ("先".to_string(), 1),
+                ("想".to_string(), 1)
+            ]
+        );
+        // Phrases for more syllables come first
+        assert_eq!(
+            phrases("xi'an"),
+            vec![("西安".to_string(), 2), ("西".to_string(), 1)]
+        );
+        // Only the last syllable can be the beginning of a syllable
+        assert_eq!(
+            phrases("xianxi"),
+            vec![("现".to_string(), 1), ("先".to_string(), 1)]
+        );
+        assert_eq!(phrases("qq"), Vec::new());
+    }
+}
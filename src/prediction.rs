@@ -0,0 +1,455 @@
+use super::capitalization::capitalized;
+use super::{EngineAction, EngineContext, Input, InputEngine, TextContext};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Number of words of the longest n-grams, so the previous words used for a prediction are one less
+const ORDER: usize = 3;
+
+/// Factor of the score of a shorter n-gram if the longer one is unknown
+const BACKOFF: f64 = 0.4;
+
+/// Maximum number of completions offered as candidates
+const MAX_CANDIDATES: usize = 5;
+
+/// Counts of the words following the previous words
+///
+/// The key is the list of previous words, from no previous word for the frequency of the single words up to
+/// ORDER - 1 previous words. All words are lowercase
+#[derive(Clone, Debug, Default)]
+struct NgramModel {
+    counts: HashMap<Vec<String>, HashMap<String, u64>>,
+    // Sum of the counts of all words following the previous words
+    totals: HashMap<Vec<String>, u64>,
+}
+
+impl NgramModel {
+    /// Counts the word after the previous words for all n-grams it ends
+    fn learn(&mut self, previous_words: &[String], word: &str) {
+        for length in 0..=previous_words.len().min(ORDER - 1) {
+            self.add(&previous_words[previous_words.len() - length..], word, 1);
+        }
+    }
+
+    /// Adds 'count' to the n-gram of the previous words and the word
+    fn add(&mut self, previous_words: &[String], word: &str, count: u64) {
+        *self
+            .counts
+            .entry(previous_words.to_vec())
+            .or_default()
+            .entry(word.to_string())
+            .or_default() += count;
+        *self.totals.entry(previous_words.to_vec()).or_default() += count;
+    }
+
+    /// Returns how often the word followed the previous words
+    fn count(&self, previous_words: &[String], word: &str) -> u64 {
+        self.counts
+            .get(previous_words)
+            .and_then(|words| words.get(word))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns how often any word followed the previous words
+    fn total(&self, previous_words: &[String]) -> u64 {
+        self.totals.get(previous_words).copied().unwrap_or(0)
+    }
+
+    /// Returns the words that followed the previous words
+    fn followers(&self, previous_words: &[String]) -> impl Iterator<Item = &String> {
+        self.counts
+            .get(previous_words)
+            .into_iter()
+            .flat_map(HashMap::keys)
+    }
+
+    /// Adds the n-grams of the text. Each line contains the count followed by the words of the n-gram:
+    ///
+    /// 42 how are you
+    ///
+    /// Lines starting with '#' are ignored
+    fn parse(&mut self, content: &str) {
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (count, words) = match fields.split_first() {
+                Some((count, words)) if !count.starts_with('#') && !words.is_empty() => {
+                    (count, words)
+                }
+                _ => continue,
+            };
+            let count = match count.parse::<u64>() {
+                Ok(count) => count,
+                Err(_) => continue,
+            };
+            let words: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+            let (word, previous_words) = words.split_last().unwrap();
+            // Only the n-gram itself is counted, because the file contains the shorter n-grams as well
+            if previous_words.len() < ORDER {
+                self.add(previous_words, word, count);
+            }
+        }
+    }
+
+    /// Returns the n-grams in the format read by parse, sorted to keep the file stable
+    fn serialize(&self) -> String {
+        let mut lines: Vec<String> = self
+            .counts
+            .iter()
+            .flat_map(|(previous_words, words)| {
+                words.iter().map(move |(word, count)| {
+                    let mut fields = vec![count.to_string()];
+                    fields.extend(previous_words.iter().cloned());
+                    fields.push(word.clone());
+                    fields.join(" ")
+                })
+            })
+            .collect();
+        lines.sort();
+        lines.iter().map(|line| line.clone() + "\n").collect()
+    }
+}
+
+/// Engine that offers completions for the word at the cursor as candidates
+///
+/// The completions are ranked by an n-gram model with the previous words of the sentence, which are taken from the
+/// surrounding text. Without a typed prefix, the most likely next words are offered. Selecting a candidate replaces
+/// the typed part of the word by the completion and a space with a single commit.
+///
+/// The model consists of a base model loaded from files and a user model that learns every word committed in front
+/// of a separator or by selecting a candidate. The user model is saved to its file when the engine is reset or dropped.
+/// The engine neither offers completions nor learns in text inputs for sensitive data
+#[derive(Debug, Default)]
+pub struct PredictionEngine {
+    base_model: NgramModel,
+    user_model: NgramModel,
+    // File of the user model or None if it is not persisted
+    user_model_path: Option<PathBuf>,
+    // True if the user model learned words since it was saved
+    changed: bool,
+    candidates: Vec<String>,
+}
+
+impl PredictionEngine {
+    /// Creates a new PredictionEngine without any n-grams that does not persist what it learns
+    pub fn new() -> Self {
+        PredictionEngine::default()
+    }
+
+    /// Creates a new PredictionEngine with the n-grams of the file at 'path' as the base model
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut engine = PredictionEngine::new();
+        engine.load_file(path)?;
+        Ok(engine)
+    }
+
+    /// Adds the n-grams of the file at 'path' to the base model
+    ///
+    /// Each line contains the count of an n-gram followed by its up to 3 words separated by spaces:
+    ///
+    /// 42 how are you
+    ///
+    /// The counts of the shorter n-grams like 'are you' and 'you' are read from their own lines.
+    /// Lines starting with '#' are ignored
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        self.base_model.parse(&content);
+        Ok(())
+    }
+
+    /// Persists the user model in the file at 'path', which has the same format as the files of the base model
+    ///
+    /// If the file exists, the user model is loaded from it
+    pub fn with_user_model<P: AsRef<Path>>(mut self, path: P) -> io::Result<Self> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(content) => self.user_model.parse(&content),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => return Err(error),
+        }
+        self.user_model_path = Some(path.as_ref().to_path_buf());
+        Ok(self)
+    }
+
+    /// Saves the user model to its file, if it learned words since it was loaded or saved
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = match &self.user_model_path {
+            Some(path) if self.changed => path,
+            _ => return Ok(()),
+        };
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, self.user_model.serialize())?;
+        self.changed = false;
+        Ok(())
+    }
+
+    /// Returns the completions for the word left of the cursor at the end of the text, the most likely first
+    ///
+    /// The completions start with the typed part of the word and are capitalized like it
+    pub fn predict(&self, text_before_cursor: &str) -> Vec<String> {
+        let context = TextContext::new(text_before_cursor, text_before_cursor.len(), ORDER - 1);
+        let prefix = context.current_word_prefix();
+        let previous_words = previous_words(&context);
+        if prefix.is_empty() && previous_words.is_empty() {
+            return Vec::new();
+        }
+        let lowercase_prefix = prefix.to_lowercase();
+        let mut words: Vec<&String> = (0..=previous_words.len())
+            .flat_map(|length| {
+                let previous_words = &previous_words[previous_words.len() - length..];
+                self.base_model
+                    .followers(previous_words)
+                    .chain(self.user_model.followers(previous_words))
+            })
+            .filter(|word| word.starts_with(&lowercase_prefix) && **word != lowercase_prefix)
+            .collect();
+        words.sort();
+        words.dedup();
+        let mut scored: Vec<(f64, &String)> = words
+            .into_iter()
+            .map(|word| (self.score(&previous_words, word), word))
+            .collect();
+        // The sort is stable, so words with the same score stay in alphabetical order
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        let capitalize = prefix.chars().next().is_some_and(char::is_uppercase);
+        scored
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|(_, word)| match capitalize {
+                true => capitalized(word),
+                false => word.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the probability of the word after the previous words, backing off to fewer previous words if the
+    /// n-gram is unknown
+    fn score(&self, previous_words: &[String], word: &str) -> f64 {
+        let mut factor = 1.0;
+        for length in (0..=previous_words.len()).rev() {
+            let previous_words = &previous_words[previous_words.len() - length..];
+            let count = self.base_model.count(previous_words, word)
+                + self.user_model.count(previous_words, word);
+            if count > 0 {
+                let total =
+                    self.base_model.total(previous_words) + self.user_model.total(previous_words);
+                return factor * count as f64 / total as f64;
+            }
+            factor *= BACKOFF;
+        }
+        0.0
+    }
+
+    /// Learns the words of the text that end at or after 'start' and are followed by a separator
+    fn learn(&mut self, text: &str, start: usize) {
+        let ends: Vec<usize> = text
+            .split_word_bound_indices()
+            .filter(|(_, segment)| segment.chars().any(char::is_alphanumeric))
+            .map(|(index, segment)| index + segment.len())
+            .filter(|end| *end >= start && *end < text.len())
+            .collect();
+        for end in ends {
+            let context = TextContext::new(text, end, ORDER - 1);
+            let word = match &context.current_word {
+                Some(word) if word.complete => word.text.to_lowercase(),
+                _ => continue,
+            };
+            self.user_model.learn(&previous_words(&context), &word);
+            self.changed = true;
+        }
+    }
+
+    /// Returns the actions to offer the completions for the text left of the cursor
+    fn update(&mut self, text_before_cursor: &str) -> Vec<EngineAction> {
+        let candidates = self.predict(text_before_cursor);
+        if candidates.is_empty() && self.candidates.is_empty() {
+            return Vec::new();
+        }
+        self.candidates = candidates;
+        vec![EngineAction::Candidates(self.candidates.clone())]
+    }
+
+    /// Replaces the typed part of the word left of the cursor with the candidate at the index
+    fn select(&mut self, index: usize, context: &EngineContext) -> Vec<EngineAction> {
+        let candidate = match self.candidates.get(index) {
+            Some(candidate) => candidate.clone(),
+            None => return Vec::new(),
+        };
+        let before = &context.text_before_cursor;
+        let prefix = TextContext::new(before, before.len(), 0)
+            .current_word_prefix()
+            .to_string();
+        let mut completion = candidate.clone();
+        if !context.text_after_cursor.starts_with(char::is_whitespace) {
+            completion.push(' ');
+        }
+        let start = before.len() - prefix.len();
+        let text = before[..start].to_string() + &completion;
+        // The word is learned even if the space already follows it in the surrounding text
+        self.learn(
+            &format!("{} ", before[..start].to_string() + &candidate),
+            start,
+        );
+        let mut actions = vec![
+            EngineAction::Delete {
+                before: prefix.chars().count(),
+                after: 0,
+            },
+            EngineAction::Commit(completion),
+        ];
+        actions.append(&mut self.update(&text));
+        actions
+    }
+}
+
+impl InputEngine for PredictionEngine {
+    fn process(&mut self, input: Input, context: &EngineContext) -> Vec<EngineAction> {
+        let before = &context.text_before_cursor;
+        let text = match &input {
+            _ if context.sensitive => None,
+            Input::Select(index) if *index < self.candidates.len() => {
+                return self.select(*index, context)
+            }
+            Input::Char(c) => Some(format!("{}{}", before, c)),
+            Input::Text(text) => Some(before.clone() + text),
+            Input::Backspace => {
+                let length = before.chars().next_back().map_or(0, char::len_utf8);
+                Some(before[..before.len() - length].to_string())
+            }
+            _ => None,
+        };
+        let mut actions = vec![EngineAction::Forward(input.clone())];
+        match text {
+            Some(text) => {
+                if matches!(input, Input::Char(_) | Input::Text(_)) {
+                    self.learn(&text, before.len());
+                }
+                actions.append(&mut self.update(&text));
+            }
+            None if !self.candidates.is_empty() => {
+                self.candidates.clear();
+                actions.push(EngineAction::Candidates(Vec::new()));
+            }
+            None => (),
+        }
+        actions
+    }
+
+    fn reset(&mut self) {
+        self.candidates.clear();
+        if let Err(_error) = self.save() {
+            #[cfg(feature = "debug")]
+            error!(
+                "Failed to save the user model of the predictions: {}",
+                _error
+            );
+        }
+    }
+}
+
+impl Drop for PredictionEngine {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+/// Returns the lowercase previous words of the sentence of the current word
+fn previous_words(context: &TextContext) -> Vec<String> {
+    if context.at_sentence_start {
+        return Vec::new();
+    }
+    context
+        .previous_words
+        .iter()
+        .map(|word| word.text.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "\
+# unigrams
+10 hello
+3 help
+5 how
+6 are
+3 you
+# bigrams and trigrams
+4 how are
+1 are you
+2 how are you
+";
+
+    fn engine() -> PredictionEngine {
+        let mut engine = PredictionEngine::new();
+        engine.base_model.parse(MODEL);
+        engine
+    }
+
+    #[test]
+    fn serialized_model_is_parsed_to_the_same_counts() {
+        let mut model = NgramModel::default();
+        model.parse(MODEL);
+        model.learn(&["how".to_string(), "are".to_string()], "they");
+        let serialized = model.serialize();
+        let mut parsed = NgramModel::default();
+        parsed.parse(&serialized);
+        assert_eq!(parsed.counts, model.counts);
+        assert_eq!(parsed.totals, model.totals);
+        assert_eq!(parsed.serialize(), serialized);
+    }
+
+    #[test]
+    fn totals_are_kept_up_to_date() {
+        let mut model = NgramModel::default();
+        model.parse(MODEL);
+        assert_eq!(model.total(&[]), 27);
+        assert_eq!(model.total(&["how".to_string()]), 4);
+        model.learn(&["how".to_string()], "is");
+        assert_eq!(model.total(&[]), 28);
+        assert_eq!(model.total(&["how".to_string()]), 5);
+    }
+
+    #[test]
+    fn longer_ngrams_rank_before_frequent_words() {
+        // 'you' follows 'how are' and is ranked before the more frequent unigrams
+        assert_eq!(engine().predict("how are ")[0], "you");
+        // Without a matching n-gram, the words are ranked by their frequency
+        assert_eq!(engine().predict("he"), vec!["hello", "help"]);
+        // The words of a previous sentence are not used, so nothing is predicted without a prefix
+        assert!(engine().predict("How are. ").is_empty());
+        assert_eq!(engine().predict("How are. Y"), vec!["You"]);
+    }
+
+    #[test]
+    fn completions_keep_the_capitalization_of_the_prefix() {
+        assert_eq!(engine().predict("He"), vec!["Hello", "Help"]);
+        assert_eq!(engine().predict("so. Ho"), vec!["How"]);
+    }
+
+    #[test]
+    fn selected_completion_replaces_the_prefix() {
+        let mut engine = engine();
+        let context = EngineContext {
+            text_before_cursor: "say he".to_string(),
+            ..EngineContext::empty()
+        };
+        engine.candidates = engine.predict("say he");
+        let actions = engine.process(Input::Select(0), &context);
+        assert_eq!(
+            actions[0],
+            EngineAction::Delete {
+                before: 2,
+                after: 0
+            }
+        );
+        assert_eq!(actions[1], EngineAction::Commit("hello ".to_string()));
+        assert_eq!(engine.user_model.count(&["say".to_string()], "hello"), 1);
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(feature = "record")]
+use serde::{Deserialize, Serialize};
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ChangeCause, ContentHint, ContentPurpose,
+};
+
+/// Event sent by the wayland server to the input method
+///
+/// Positions and lengths are in bytes
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "record",
+    derive(Serialize, Deserialize),
+    serde(tag = "event", rename_all = "snake_case")
+)]
+pub enum Event {
+    Activate,
+    Deactivate,
+    SurroundingText {
+        text: String,
+        cursor: usize,
+        anchor: usize,
+    },
+    TextChangeCause {
+        #[cfg_attr(feature = "record", serde(with = "raw::change_cause"))]
+        cause: ChangeCause,
+    },
+    ContentType {
+        #[cfg_attr(feature = "record", serde(with = "raw::content_hint"))]
+        hint: ContentHint,
+        #[cfg_attr(feature = "record", serde(with = "raw::content_purpose"))]
+        purpose: ContentPurpose,
+    },
+    Done,
+    Unavailable,
+}
+
+/// Request sent by the input method to the wayland server
+///
+/// Positions and lengths are in bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "record",
+    derive(Serialize, Deserialize),
+    serde(tag = "request", rename_all = "snake_case")
+)]
+pub enum Request {
+    CommitString {
+        text: String,
+    },
+    DeleteSurroundingText {
+        before_length: usize,
+        after_length: usize,
+    },
+    SetPreeditString {
+        text: String,
+        cursor_begin: i32,
+        cursor_end: i32,
+    },
+    Commit {
+        serial: u32,
+    },
+}
+
+/// Serializes the enums of the protocol as their raw values
+#[cfg(feature = "record")]
+mod raw {
+    macro_rules! raw_serde {
+        ($module:ident, $type:ty) => {
+            pub mod $module {
+                use super::super::*;
+                use serde::{de::Error, Deserializer, Serializer};
+
+                pub fn serialize<S: Serializer>(
+                    value: &$type,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_u32(value.to_raw())
+                }
+
+                pub fn deserialize<'de, D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<$type, D::Error> {
+                    let raw = u32::deserialize(deserializer)?;
+                    <$type>::from_raw(raw).ok_or_else(|| {
+                        D::Error::custom(format!("invalid {}: {}", stringify!($module), raw))
+                    })
+                }
+            }
+        };
+    }
+
+    raw_serde!(change_cause, ChangeCause);
+    raw_serde!(content_hint, ContentHint);
+    raw_serde!(content_purpose, ContentPurpose);
+}
@@ -0,0 +1,134 @@
+use super::{Event, HintPurpose, IMService, IMVisibility, ReceiveSurroundingText, Request};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Entry of a recorded session
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    /// Event that was sent by the wayland server
+    Event(Event),
+    /// Request that was sent to the wayland server
+    Request(Request),
+}
+
+/// Writes every event and request of an IMService to a file with one JSON object per line
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Creates the file at 'path' or truncates it if it already exists
+    pub(crate) fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder> {
+        Ok(Recorder {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Appends the record to the file
+    ///
+    /// If 'sensitive' is true, every char of the texts is replaced by as many '*' as the char has bytes,
+    /// so the positions within the texts stay valid
+    pub(crate) fn record(&mut self, record: Record, sensitive: bool) -> io::Result<()> {
+        let record = match sensitive {
+            true => mask(record),
+            false => record,
+        };
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        // Keep the file complete in case the input method crashes
+        self.writer.flush()
+    }
+}
+
+/// Replaces the texts of the record with '*'
+fn mask(record: Record) -> Record {
+    let mask_text = |text: String| "*".repeat(text.len());
+    match record {
+        Record::Event(Event::SurroundingText {
+            text,
+            cursor,
+            anchor,
+        }) => Record::Event(Event::SurroundingText {
+            text: mask_text(text),
+            cursor,
+            anchor,
+        }),
+        Record::Request(Request::CommitString { text }) => Record::Request(Request::CommitString {
+            text: mask_text(text),
+        }),
+        Record::Request(Request::SetPreeditString {
+            text,
+            cursor_begin,
+            cursor_end,
+        }) => Record::Request(Request::SetPreeditString {
+            text: mask_text(text),
+            cursor_begin,
+            cursor_end,
+        }),
+        record => record,
+    }
+}
+
+/// Reads a recorded session to reproduce it without a wayland server
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replayer {
+    records: Vec<Record>,
+}
+
+impl Replayer {
+    /// Reads the session recorded in the file at 'path'
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Replayer> {
+        let mut records = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line)?);
+        }
+        Ok(Replayer { records })
+    }
+
+    /// Returns the recorded events and requests in the order they were recorded
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Feeds the recorded events and requests into the IMService in the order they were recorded
+    ///
+    /// The IMService should be created with IMService::new_detached() so no requests are sent to a wayland server.
+    /// The connectors of the IMService get notified like they were during the recorded session
+    pub fn replay<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText>(
+        &self,
+        im_service: &IMService<T, D>,
+    ) {
+        for record in &self.records {
+            match record.clone() {
+                Record::Event(event) => im_service.handle_event(event),
+                Record::Request(request) => im_service
+                    .im_service_arc
+                    .lock()
+                    .unwrap()
+                    .replay_request(request),
+            }
+        }
+    }
+
+    /// Feeds only the recorded events into the IMService
+    ///
+    /// This allows the input method to react to the events with its own requests, for example to test a new version of it
+    pub fn replay_events<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText>(
+        &self,
+        im_service: &IMService<T, D>,
+    ) {
+        for record in &self.records {
+            if let Record::Event(event) = record {
+                im_service.handle_event(event.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
+
+/// Linux input event code of the enter key
+pub const KEY_ENTER: u32 = input_event_codes::KEY_ENTER!();
+
+/// What the return key should do in the current text input
+///
+/// Only multiline text inputs get a line break committed. For all other text inputs the enter key is sent,
+/// so the application can submit the text. The text-input protocol does not tell if a single line text input is
+/// used for searching or sending messages, so those text inputs get ReturnKeyAction::Enter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReturnKeyAction {
+    /// Commits a line break
+    Newline,
+    /// Sends the enter key
+    Enter,
+    /// Sends the enter key to open the URL
+    Go,
+    /// Sends the enter key to continue with the next field of a form
+    Next,
+    /// Sends the enter key to finish entering a number or a secret
+    Done,
+}
+
+impl ReturnKeyAction {
+    /// Resolves the action of the return key from the content type of the text input
+    pub fn resolve(hint: ContentHint, purpose: ContentPurpose) -> ReturnKeyAction {
+        if hint.contains(ContentHint::Multiline) {
+            return ReturnKeyAction::Newline;
+        }
+        match purpose {
+            ContentPurpose::Url => ReturnKeyAction::Go,
+            ContentPurpose::Email
+            | ContentPurpose::Name
+            | ContentPurpose::Phone
+            | ContentPurpose::Date
+            | ContentPurpose::Time
+            | ContentPurpose::Datetime => ReturnKeyAction::Next,
+            ContentPurpose::Digits
+            | ContentPurpose::Number
+            | ContentPurpose::Password
+            | ContentPurpose::Pin => ReturnKeyAction::Done,
+            _ => ReturnKeyAction::Enter,
+        }
+    }
+
+    /// Returns the label for the return key
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReturnKeyAction::Newline | ReturnKeyAction::Enter => "↵",
+            ReturnKeyAction::Go => "Go",
+            ReturnKeyAction::Next => "Next",
+            ReturnKeyAction::Done => "Done",
+        }
+    }
+
+    /// Returns true if the enter key has to be sent instead of committing a line break
+    pub fn sends_key(&self) -> bool {
+        *self != ReturnKeyAction::Newline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_are_resolved_by_purpose() {
+        let cases = [
+            (ContentPurpose::Normal, ReturnKeyAction::Enter),
+            (ContentPurpose::Alpha, ReturnKeyAction::Enter),
+            (ContentPurpose::Terminal, ReturnKeyAction::Enter),
+            (ContentPurpose::Url, ReturnKeyAction::Go),
+            (ContentPurpose::Email, ReturnKeyAction::Next),
+            (ContentPurpose::Name, ReturnKeyAction::Next),
+            (ContentPurpose::Phone, ReturnKeyAction::Next),
+            (ContentPurpose::Date, ReturnKeyAction::Next),
+            (ContentPurpose::Time, ReturnKeyAction::Next),
+            (ContentPurpose::Datetime, ReturnKeyAction::Next),
+            (ContentPurpose::Digits, ReturnKeyAction::Done),
+            (ContentPurpose::Number, ReturnKeyAction::Done),
+            (ContentPurpose::Password, ReturnKeyAction::Done),
+            (ContentPurpose::Pin, ReturnKeyAction::Done),
+        ];
+        for (purpose, expected) in cases {
+            let action = ReturnKeyAction::resolve(ContentHint::None, purpose);
+            assert_eq!(action, expected, "{:?}", purpose);
+            assert!(action.sends_key());
+        }
+    }
+
+    #[test]
+    fn multiline_inputs_get_a_newline() {
+        for purpose in [
+            ContentPurpose::Normal,
+            ContentPurpose::Url,
+            ContentPurpose::Email,
+            ContentPurpose::Number,
+        ] {
+            let hint = ContentHint::Multiline | ContentHint::Spellcheck;
+            let action = ReturnKeyAction::resolve(hint, purpose);
+            assert_eq!(action, ReturnKeyAction::Newline, "{:?}", purpose);
+            assert!(!action.sends_key());
+            assert_eq!(action.label(), "↵");
+        }
+    }
+}
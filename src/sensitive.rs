@@ -0,0 +1,12 @@
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
+
+/// Returns true if the text input contains sensitive data like passwords
+///
+/// Text from sensitive text inputs must not be logged or stored, for example by learning dictionaries or a history
+pub fn is_sensitive(hint: ContentHint, purpose: ContentPurpose) -> bool {
+    hint.intersects(ContentHint::SensitiveData | ContentHint::HiddenText)
+        || purpose == ContentPurpose::Password
+        || purpose == ContentPurpose::Pin
+}
@@ -0,0 +1,310 @@
+use super::{EngineAction, EngineContext, Input, InputEngine};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Format of the {date} placeholder
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Format of the {time} placeholder
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Engine that expands abbreviations like ';addr' to longer texts
+///
+/// As soon as the text left of the cursor ends with a trigger, the trigger is deleted and replaced by its expansion
+/// with a single commit. If a trigger is the beginning of another trigger, only the shorter one can be typed.
+/// Triggers that start with a letter or a digit are only expanded at the start of a word, so 'btw' is not expanded
+/// in 'subtw'. Triggers that start with punctuation like ';addr' are expanded anywhere.
+///
+/// Expansions can contain placeholders, which are replaced when the snippet is expanded:
+///
+/// {date} -> the current date like 2024-01-31
+///
+/// {time} -> the current time like 13:37
+///
+/// {date:FORMAT} -> the current date and time in a format like '%d.%m.%Y' as used by strftime
+///
+/// {clipboard} -> the text returned by the clipboard function. Without one, it is replaced by nothing
+///
+/// Braces are written as '{{' and '}}'. Unknown placeholders are kept unchanged.
+/// The engine is disabled in text inputs for sensitive data
+#[derive(Default)]
+pub struct SnippetEngine {
+    snippets: HashMap<String, String>,
+    clipboard: Option<Box<dyn Fn() -> Option<String> + Send>>,
+}
+
+impl SnippetEngine {
+    /// Creates a new SnippetEngine without any snippets
+    pub fn new() -> Self {
+        SnippetEngine::default()
+    }
+
+    /// Creates a new SnippetEngine with the snippets of the JSON file at 'path'
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut engine = SnippetEngine::new();
+        engine.load_file(path)?;
+        Ok(engine)
+    }
+
+    /// Adds the snippets of the JSON file at 'path'. Snippets with the same trigger are replaced
+    ///
+    /// The file contains an object with the triggers as keys and the expansions as values:
+    ///
+    /// { ";addr": "Street 1, 12345 City", ";today": "{date}" }
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        let snippets: HashMap<String, String> = serde_json::from_str(&content)?;
+        self.snippets.extend(snippets);
+        Ok(())
+    }
+
+    /// Adds a snippet. An existing snippet with the same trigger is replaced
+    pub fn add_snippet(&mut self, trigger: String, expansion: String) {
+        self.snippets.insert(trigger, expansion);
+    }
+
+    /// Sets the function that returns the text for the {clipboard} placeholder
+    ///
+    /// The input method protocol does not give access to the clipboard, so the application has to read it
+    pub fn with_clipboard<F: Fn() -> Option<String> + Send + 'static>(
+        mut self,
+        clipboard: F,
+    ) -> Self {
+        self.clipboard = Some(Box::new(clipboard));
+        self
+    }
+
+    /// Returns the longest trigger the text ends with
+    fn find_trigger(&self, text: &str) -> Option<&str> {
+        self.snippets
+            .keys()
+            .filter(|trigger| !trigger.is_empty() && text.ends_with(trigger.as_str()))
+            .filter(|trigger| {
+                let before = &text[..text.len() - trigger.len()];
+                !trigger.starts_with(char::is_alphanumeric)
+                    || !before.ends_with(char::is_alphanumeric)
+            })
+            .max_by_key(|trigger| trigger.len())
+            .map(String::as_str)
+    }
+
+    /// Replaces the placeholders of the expansion
+    fn expand(&self, expansion: &str) -> String {
+        let mut result = String::new();
+        let mut rest = expansion;
+        while let Some(index) = rest.find(['{', '}']) {
+            result.push_str(&rest[..index]);
+            rest = &rest[index..];
+            if let Some(tail) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+                result.push_str(&rest[..1]);
+                rest = tail;
+                continue;
+            }
+            let end = match rest.find('}') {
+                Some(end) if rest.starts_with('{') => end,
+                _ => {
+                    result.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+            match self.placeholder(&rest[1..end]) {
+                Some(value) => result.push_str(&value),
+                None => result.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Returns the value of the placeholder or None if it is unknown
+    fn placeholder(&self, name: &str) -> Option<String> {
+        match name {
+            "date" => format_now(DATE_FORMAT),
+            "time" => format_now(TIME_FORMAT),
+            "clipboard" => Some(
+                self.clipboard
+                    .as_ref()
+                    .and_then(|clipboard| clipboard())
+                    .unwrap_or_default(),
+            ),
+            name => format_now(name.strip_prefix("date:")?),
+        }
+    }
+}
+
+impl InputEngine for SnippetEngine {
+    fn process(&mut self, input: Input, context: &EngineContext) -> Vec<EngineAction> {
+        let typed = match &input {
+            Input::Char(c) => c.to_string(),
+            Input::Text(text) => text.clone(),
+            _ => return vec![EngineAction::Forward(input)],
+        };
+        if context.sensitive {
+            return vec![EngineAction::Forward(input)];
+        }
+        let text = context.text_before_cursor.clone() + &typed;
+        let trigger = match self.find_trigger(&text) {
+            Some(trigger) => trigger,
+            None => return vec![EngineAction::Forward(input)],
+        };
+        let expansion = self.expand(&self.snippets[trigger]);
+        // The trigger can start in the surrounding text or within the typed text
+        let trigger_len = trigger.chars().count();
+        let typed_len = typed.chars().count();
+        let kept: String = typed
+            .chars()
+            .take(typed_len.saturating_sub(trigger_len))
+            .collect();
+        vec![
+            EngineAction::Delete {
+                before: trigger_len.saturating_sub(typed_len),
+                after: 0,
+            },
+            EngineAction::Commit(kept + &expansion),
+        ]
+    }
+}
+
+/// Returns the current local date and time in the strftime format or None if the format is invalid
+fn format_now(format: &str) -> Option<String> {
+    let mut result = String::new();
+    write!(result, "{}", chrono::Local::now().format(format)).ok()?;
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> SnippetEngine {
+        let mut engine = SnippetEngine::new();
+        engine.add_snippet(";addr".to_string(), "Street 1".to_string());
+        engine.add_snippet("btw".to_string(), "by the way".to_string());
+        engine
+    }
+
+    fn process(engine: &mut SnippetEngine, before: &str, input: Input) -> Vec<EngineAction> {
+        let context = EngineContext {
+            text_before_cursor: before.to_string(),
+            ..EngineContext::empty()
+        };
+        engine.process(input, &context)
+    }
+
+    #[test]
+    fn braces_are_escaped() {
+        let engine = SnippetEngine::new();
+        assert_eq!(engine.expand("{{x}} }} {{"), "{x} } {");
+        assert_eq!(engine.expand("{{date}}"), "{date}");
+    }
+
+    #[test]
+    fn dates_are_formatted() {
+        let engine = SnippetEngine::new();
+        let year = chrono::Local::now().format("%Y").to_string();
+        assert_eq!(engine.expand("{date:%Y}!"), format!("{}!", year));
+        assert_eq!(engine.expand("{date:100%%}"), "100%");
+        let date = engine.expand("{date}");
+        assert_eq!(date.len(), "2024-01-31".len());
+        assert!(date.starts_with(&year));
+        assert_eq!(engine.expand("{time}").len(), "13:37".len());
+    }
+
+    #[test]
+    fn clipboard_is_inserted() {
+        assert_eq!(SnippetEngine::new().expand("a{clipboard}b"), "ab");
+        let engine = SnippetEngine::new().with_clipboard(|| Some("clip".to_string()));
+        assert_eq!(engine.expand("a{clipboard}b"), "aclipb");
+        let engine = SnippetEngine::new().with_clipboard(|| None);
+        assert_eq!(engine.expand("a{clipboard}b"), "ab");
+    }
+
+    #[test]
+    fn unknown_and_unterminated_placeholders_are_kept() {
+        let engine = SnippetEngine::new();
+        assert_eq!(engine.expand("{name} {}"), "{name} {}");
+        assert_eq!(engine.expand("a {date"), "a {date");
+        assert_eq!(engine.expand("a } b"), "a } b");
+        assert_eq!(engine.expand("{date:%Q}"), "{date:%Q}");
+    }
+
+    #[test]
+    fn triggers_are_replaced() {
+        let mut engine = engine();
+        assert_eq!(
+            process(&mut engine, "Home: ;add", Input::Char('r')),
+            vec![
+                EngineAction::Delete {
+                    before: 4,
+                    after: 0
+                },
+                EngineAction::Commit("Street 1".to_string()),
+            ]
+        );
+        // The trigger is split between the surrounding text and the typed text
+        assert_eq!(
+            process(&mut engine, "Home: ;a", Input::Text("ddr".to_string())),
+            vec![
+                EngineAction::Delete {
+                    before: 2,
+                    after: 0
+                },
+                EngineAction::Commit("Street 1".to_string()),
+            ]
+        );
+        // The trigger is within the typed text
+        assert_eq!(
+            process(&mut engine, "", Input::Text("at;addr".to_string())),
+            vec![
+                EngineAction::Delete {
+                    before: 0,
+                    after: 0
+                },
+                EngineAction::Commit("atStreet 1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn word_triggers_are_only_replaced_at_the_start_of_words() {
+        let mut engine = engine();
+        assert_eq!(
+            process(&mut engine, "sub", Input::Text("tw".to_string())),
+            vec![EngineAction::Forward(Input::Text("tw".to_string()))]
+        );
+        assert_eq!(
+            process(&mut engine, "ok (bt", Input::Char('w')),
+            vec![
+                EngineAction::Delete {
+                    before: 2,
+                    after: 0
+                },
+                EngineAction::Commit("by the way".to_string()),
+            ]
+        );
+        // Triggers starting with punctuation are replaced within words
+        assert_eq!(
+            process(&mut engine, "x;add", Input::Char('r'))[1],
+            EngineAction::Commit("Street 1".to_string())
+        );
+    }
+
+    #[test]
+    fn sensitive_text_inputs_are_not_expanded() {
+        let mut engine = engine();
+        let context = EngineContext {
+            text_before_cursor: "bt".to_string(),
+            sensitive: true,
+            ..EngineContext::empty()
+        };
+        assert_eq!(
+            engine.process(Input::Char('w'), &context),
+            vec![EngineAction::Forward(Input::Char('w'))]
+        );
+    }
+}
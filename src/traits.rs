@@ -1,4 +1,4 @@
-use super::SubmitError;
+use super::{Divergence, ShiftState, SubmitError, TextDelta};
 use wayland_client::{protocol::wl_seat::WlSeat, Main};
 use wayland_protocols::misc::zwp_input_method_v2::client::zwp_input_method_manager_v2::ZwpInputMethodManagerV2;
 use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
@@ -50,14 +50,92 @@ pub trait InputMethod<T: IMVisibility + HintPurpose, D: ReceiveSurroundingText>
 pub trait IMVisibility {
     fn activate_im(&self);
     fn deactivate_im(&self);
+
+    /// Called by the Pipeline whenever the candidates of its engines change, so they can be shown in a popup
+    ///
+    /// An empty list hides the candidates. The default implementation ignores the notification
+    fn show_candidates(&self, _candidates: &[String]) {}
 }
 
 /// Trait to get notified when the text surrounding the cursor changes
 pub trait ReceiveSurroundingText {
     fn text_changed(&self, string_left_of_cursor: String, string_right_of_cursor: String);
+
+    /// Called with the difference between the old and the new surrounding text whenever the text changes
+    ///
+    /// Connectors that keep their own copy of the text can opt in to incremental updates by implementing this method.
+    /// The default implementation ignores the notification
+    fn text_delta(&self, _delta: TextDelta) {}
+
+    /// Returns if text_changed() should be called with the full strings left and right of the cursor
+    ///
+    /// Connectors that only use text_delta() can return false to avoid copying the whole surrounding text on every change
+    fn wants_full_text(&self) -> bool {
+        true
+    }
+
+    /// Called when the surrounding text confirmed by the wayland server differs from the text predicted after a commit
+    ///
+    /// The surrounding text is resynchronized with the confirmed text right after this notification.
+    /// The default implementation ignores the notification
+    fn text_diverged(&self, _divergence: Divergence) {}
+
+    /// Called when the cursor or the anchor moved, even if the surrounding text stayed the same
+    ///
+    /// This happens for example if the user taps somewhere else in the same text field.
+    /// The default implementation ignores the notification
+    fn cursor_changed(&self, _position: CursorPosition) {}
+}
+
+/// Position of the cursor and the anchor within the surrounding text
+///
+/// The positions are provided as byte offsets, like they are sent by the wayland server, and as char offsets.
+/// If nothing is selected, the anchor is at the same position as the cursor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CursorPosition {
+    pub cursor_byte: usize,
+    pub cursor_char: usize,
+    pub anchor_byte: usize,
+    pub anchor_char: usize,
+}
+
+impl CursorPosition {
+    /// Creates a new CursorPosition from the byte offsets of the cursor and the anchor within 'text'
+    ///
+    /// Offsets that are not on a char boundary are counted up to the last complete char before them
+    pub fn new(text: &str, cursor_byte: usize, anchor_byte: usize) -> Self {
+        CursorPosition {
+            cursor_byte,
+            cursor_char: char_count_up_to(text, cursor_byte),
+            anchor_byte,
+            anchor_char: char_count_up_to(text, anchor_byte),
+        }
+    }
+
+    /// Returns true if text between the cursor and the anchor is selected
+    pub fn has_selection(&self) -> bool {
+        self.cursor_byte != self.anchor_byte
+    }
+}
+
+/// Returns the number of chars in 'text' that end at or before the byte offset 'byte'
+fn char_count_up_to(text: &str, byte: usize) -> usize {
+    text.char_indices()
+        .take_while(|(index, character)| index + character.len_utf8() <= byte)
+        .count()
 }
 
 /// Trait to get notified when the hint or the purpose of the content changes
 pub trait HintPurpose {
     fn set_hint_purpose(&self, content_hint: ContentHint, content_purpose: ContentPurpose);
+
+    /// Called after every change with the advice whether the next letter should be capitalized
+    ///
+    /// This is only called if auto capitalization was enabled. The default implementation ignores the advice
+    fn set_shift_state(&self, _shift_state: ShiftState) {}
+
+    /// Called when the input method gets activated and whenever the text input starts or stops containing sensitive data
+    ///
+    /// While the text input is sensitive, no text should be stored or learned. The default implementation ignores the notification
+    fn set_sensitive(&self, _sensitive: bool) {}
 }
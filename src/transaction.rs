@@ -0,0 +1,279 @@
+use super::{Request, SubmitError};
+
+/// Preedit string shown at the cursor position of the text input
+///
+/// The preedit string is not part of the surrounding text. It is replaced or removed with every commit
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    /// Byte offset of the start of the cursor within the text or -1 to hide the cursor
+    pub cursor_begin: i32,
+    /// Byte offset of the end of the cursor within the text or -1 to hide the cursor
+    pub cursor_end: i32,
+}
+
+impl Preedit {
+    /// Creates a new Preedit with the cursor at the end of the text
+    pub fn new(text: String) -> Self {
+        let cursor = text.len() as i32;
+        Preedit {
+            text,
+            cursor_begin: cursor,
+            cursor_end: cursor,
+        }
+    }
+
+    /// Checks if the cursor is hidden or if both ends of the cursor are on char boundaries within the text
+    pub(crate) fn validate(&self) -> Result<(), SubmitError> {
+        if self.cursor_begin == -1 || self.cursor_end == -1 {
+            return Ok(());
+        }
+        let is_valid = |offset: i32| offset >= 0 && self.text.is_char_boundary(offset as usize);
+        match is_valid(self.cursor_begin) && is_valid(self.cursor_end) {
+            true => Ok(()),
+            false => Err(SubmitError::InvalidPreeditCursor),
+        }
+    }
+}
+
+/// Collects requests that are sent to the wayland server and applied together with a single commit
+///
+/// The wayland server applies the requests of a commit in a fixed order, regardless of the order they were sent in:
+///
+/// 1. The surrounding text is deleted
+///
+/// 2. The commit string is inserted at the cursor
+///
+/// 3. The preedit string is shown at the new cursor position
+///
+/// Each kind of request can only be part of a transaction once. Setting it again replaces the previous value
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transaction {
+    pub(crate) delete: Option<(usize, usize)>,
+    pub(crate) commit_string: Option<String>,
+    pub(crate) preedit: Option<Preedit>,
+}
+
+impl Transaction {
+    /// Creates a new empty Transaction
+    pub fn new() -> Self {
+        Transaction::default()
+    }
+
+    /// Deletes text around the cursor
+    ///
+    /// INPUTS:
+    ///
+    /// before -> number of bytes to delete from the surrounding_text going left from the cursor
+    ///
+    /// after  -> number of bytes to delete from the surrounding_text going right from the cursor
+    pub fn delete_surrounding_text(mut self, before: usize, after: usize) -> Self {
+        self.delete = Some((before, after));
+        self
+    }
+
+    /// Inserts 'text' at the cursor after the surrounding text was deleted
+    pub fn commit_string(mut self, text: String) -> Self {
+        self.commit_string = Some(text);
+        self
+    }
+
+    /// Shows 'text' as the preedit string with the cursor at the end of it
+    pub fn preedit_string(self, text: String) -> Self {
+        self.preedit(Preedit::new(text))
+    }
+
+    /// Shows the preedit string with a custom cursor
+    pub fn preedit(mut self, preedit: Preedit) -> Self {
+        self.preedit = Some(preedit);
+        self
+    }
+
+    /// Returns the transaction with the requests of 'newer' replacing those of the same kind
+    ///
+    /// This is what the wayland server does with requests that are sent again before the commit
+    pub(crate) fn merge(self, newer: Transaction) -> Transaction {
+        Transaction {
+            delete: newer.delete.or(self.delete),
+            commit_string: newer.commit_string.or(self.commit_string),
+            preedit: newer.preedit.or(self.preedit),
+        }
+    }
+
+    /// Returns true if the transaction does not contain any requests
+    pub fn is_empty(&self) -> bool {
+        self.delete.is_none() && self.commit_string.is_none() && self.preedit.is_none()
+    }
+
+    /// Checks if the transaction can be applied to 'text' with the cursor at the byte offset 'cursor'
+    ///
+    /// The range to delete has to be within the text and must not split a char
+    pub(crate) fn validate(&self, text: &str, cursor: usize) -> Result<(), SubmitError> {
+        if let Some((before, after)) = self.delete {
+            let is_valid = before <= cursor
+                && cursor + after <= text.len()
+                && text.is_char_boundary(cursor - before)
+                && text.is_char_boundary(cursor + after);
+            if !is_valid {
+                return Err(SubmitError::InvalidDeleteRange);
+            }
+        }
+        match &self.preedit {
+            Some(preedit) => preedit.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the requests of the transaction without the final commit
+    pub(crate) fn requests(&self) -> Vec<Request> {
+        let mut requests = Vec::new();
+        if let Some((before, after)) = self.delete {
+            requests.push(Request::DeleteSurroundingText {
+                before_length: before,
+                after_length: after,
+            });
+        }
+        if let Some(text) = &self.commit_string {
+            requests.push(Request::CommitString { text: text.clone() });
+        }
+        if let Some(preedit) = &self.preedit {
+            requests.push(Request::SetPreeditString {
+                text: preedit.text.clone(),
+                cursor_begin: preedit.cursor_begin,
+                cursor_end: preedit.cursor_end,
+            });
+        }
+        requests
+    }
+
+    /// Applies the deletion and the commit string to 'text' in the order the wayland server applies them
+    ///
+    /// The transaction must have been validated for 'text' and 'cursor'. Returns the new position of the cursor
+    pub(crate) fn apply(&self, text: &mut String, cursor: usize) -> usize {
+        let mut cursor = cursor;
+        if let Some((before, after)) = self.delete {
+            text.replace_range(cursor - before..cursor + after, "");
+            cursor -= before;
+        }
+        if let Some(commit_string) = &self.commit_string {
+            text.insert_str(cursor, commit_string);
+            cursor += commit_string.len();
+        }
+        cursor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(transaction: &Transaction, text: &str, cursor: usize) -> (String, usize) {
+        let mut text = text.to_string();
+        let cursor = transaction.apply(&mut text, cursor);
+        (text, cursor)
+    }
+
+    #[test]
+    fn deletes_before_inserting() {
+        let transaction = Transaction::new()
+            .commit_string("XY".to_string())
+            .delete_surrounding_text(1, 1);
+        assert_eq!(transaction.validate("abcd", 2), Ok(()));
+        assert_eq!(apply(&transaction, "abcd", 2), ("aXYd".to_string(), 3));
+    }
+
+    #[test]
+    fn applies_to_multibyte_text() {
+        // 'ö' and 'ü' have 2 bytes
+        let transaction = Transaction::new()
+            .delete_surrounding_text(2, 2)
+            .commit_string("€".to_string());
+        assert_eq!(transaction.validate("aöüb", 3), Ok(()));
+        assert_eq!(apply(&transaction, "aöüb", 3), ("a€b".to_string(), 4));
+    }
+
+    #[test]
+    fn applies_with_the_cursor_at_the_start_and_at_the_end() {
+        let insert = Transaction::new().commit_string("x".to_string());
+        assert_eq!(apply(&insert, "ab", 0), ("xab".to_string(), 1));
+        assert_eq!(apply(&insert, "ab", 2), ("abx".to_string(), 3));
+
+        let delete_after = Transaction::new().delete_surrounding_text(0, 2);
+        assert_eq!(delete_after.validate("ab", 0), Ok(()));
+        assert_eq!(apply(&delete_after, "ab", 0), (String::new(), 0));
+
+        let delete_before = Transaction::new().delete_surrounding_text(2, 0);
+        assert_eq!(delete_before.validate("ab", 2), Ok(()));
+        assert_eq!(apply(&delete_before, "ab", 2), (String::new(), 0));
+    }
+
+    #[test]
+    fn rejects_deletions_out_of_range() {
+        let before = Transaction::new().delete_surrounding_text(1, 0);
+        assert_eq!(
+            before.validate("ab", 0),
+            Err(SubmitError::InvalidDeleteRange)
+        );
+        let after = Transaction::new().delete_surrounding_text(0, 1);
+        assert_eq!(
+            after.validate("ab", 2),
+            Err(SubmitError::InvalidDeleteRange)
+        );
+        let both = Transaction::new().delete_surrounding_text(2, 2);
+        assert_eq!(
+            both.validate("abc", 1),
+            Err(SubmitError::InvalidDeleteRange)
+        );
+    }
+
+    #[test]
+    fn rejects_deletions_that_split_a_char() {
+        let transaction = Transaction::new().delete_surrounding_text(1, 0);
+        assert_eq!(
+            transaction.validate("aö", 3),
+            Err(SubmitError::InvalidDeleteRange)
+        );
+        let transaction = Transaction::new().delete_surrounding_text(0, 1);
+        assert_eq!(
+            transaction.validate("öa", 0),
+            Err(SubmitError::InvalidDeleteRange)
+        );
+    }
+
+    #[test]
+    fn rejects_preedit_cursors_that_split_a_char() {
+        let preedit = Preedit {
+            text: "ö".to_string(),
+            cursor_begin: 1,
+            cursor_end: 1,
+        };
+        let transaction = Transaction::new().preedit(preedit);
+        assert_eq!(
+            transaction.validate("", 0),
+            Err(SubmitError::InvalidPreeditCursor)
+        );
+        let hidden = Transaction::new().preedit(Preedit {
+            text: "ö".to_string(),
+            cursor_begin: -1,
+            cursor_end: -1,
+        });
+        assert_eq!(hidden.validate("", 0), Ok(()));
+    }
+
+    #[test]
+    fn newer_requests_replace_older_ones_of_the_same_kind() {
+        let older = Transaction::new()
+            .commit_string("a".to_string())
+            .delete_surrounding_text(1, 0);
+        let newer = Transaction::new()
+            .commit_string("b".to_string())
+            .preedit_string("c".to_string());
+        assert_eq!(
+            older.merge(newer),
+            Transaction::new()
+                .delete_surrounding_text(1, 0)
+                .commit_string("b".to_string())
+                .preedit_string("c".to_string())
+        );
+    }
+}
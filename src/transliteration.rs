@@ -0,0 +1,275 @@
+use super::{EngineAction, EngineContext, Input, InputEngine};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Engine that transliterates typed text with a table of rules, like Latin letters to Cyrillic letters
+///
+/// The input of the longest matching rule is replaced by its output. While the typed chars could still become
+/// part of a longer rule, the output of the best match so far is shown as the preedit string. The text is committed
+/// as soon as no longer rule can match. Typed chars without a rule are committed unchanged.
+///
+/// The rules are loaded from files with the syntax of the rules in the map sections of m17n input methods:
+///
+/// ("sh" "ш") ("s" "с") ; comment
+///
+/// Everything that is not a rule, like the other sections of an m17n input method, is ignored
+#[derive(Clone, Debug, Default)]
+pub struct TransliterationEngine {
+    rules: HashMap<String, String>,
+    // All beginnings of the inputs of the rules, that are shorter than the input
+    prefixes: HashSet<String>,
+    // Typed chars that are not committed yet
+    typed: String,
+}
+
+impl TransliterationEngine {
+    /// Creates a new TransliterationEngine without any rules
+    pub fn new() -> Self {
+        TransliterationEngine::default()
+    }
+
+    /// Creates a new TransliterationEngine with the rules of the file at 'path'
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut engine = TransliterationEngine::new();
+        engine.load_file(path)?;
+        Ok(engine)
+    }
+
+    /// Adds the rules of the file at 'path'. Rules with the same input are replaced
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        for line in content.lines() {
+            for (input, output) in parse_rules(line) {
+                self.add_rule(input, output);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a rule. A rule with the same input is replaced
+    pub fn add_rule(&mut self, input: String, output: String) {
+        if input.is_empty() {
+            return;
+        }
+        for (index, _) in input.char_indices().skip(1) {
+            self.prefixes.insert(input[..index].to_string());
+        }
+        self.rules.insert(input, output);
+    }
+
+    /// Returns the output of the longest rule the text starts with and the length of its input
+    fn longest_match(&self, text: &str) -> Option<(&str, usize)> {
+        text.char_indices()
+            .map(|(index, c)| index + c.len_utf8())
+            .rev()
+            .find_map(|end| {
+                self.rules
+                    .get(&text[..end])
+                    .map(|output| (output.as_str(), end))
+            })
+    }
+
+    /// Transliterates the typed chars that can not become part of a longer rule anymore and returns them
+    ///
+    /// If 'flush' is set, all typed chars are transliterated
+    fn resolve(&mut self, flush: bool) -> String {
+        let mut resolved = String::new();
+        while !self.typed.is_empty() && (flush || !self.prefixes.contains(&self.typed)) {
+            let length = match self.longest_match(&self.typed) {
+                Some((output, length)) => {
+                    resolved.push_str(output);
+                    length
+                }
+                // The first char is not part of any rule
+                None => {
+                    let length = self.typed.chars().next().map_or(0, char::len_utf8);
+                    resolved.push_str(&self.typed[..length]);
+                    length
+                }
+            };
+            self.typed.replace_range(..length, "");
+        }
+        resolved
+    }
+
+    /// Returns the preedit string, which shows the output of the best match for the typed chars
+    fn preedit(&self) -> String {
+        let mut preedit = String::new();
+        let mut rest = self.typed.as_str();
+        while !rest.is_empty() {
+            let length = match self.longest_match(rest) {
+                Some((output, length)) => {
+                    preedit.push_str(output);
+                    length
+                }
+                None => {
+                    let length = rest.chars().next().map_or(0, char::len_utf8);
+                    preedit.push_str(&rest[..length]);
+                    length
+                }
+            };
+            rest = &rest[length..];
+        }
+        preedit
+    }
+
+    /// Returns the actions to commit the resolved text and to show the rest as the preedit string
+    fn update(&mut self, flush: bool) -> Vec<EngineAction> {
+        let resolved = self.resolve(flush);
+        let mut actions = vec![EngineAction::Preedit(self.preedit())];
+        if !resolved.is_empty() {
+            actions.push(EngineAction::Commit(resolved));
+        }
+        actions
+    }
+}
+
+impl InputEngine for TransliterationEngine {
+    fn process(&mut self, input: Input, _context: &EngineContext) -> Vec<EngineAction> {
+        match input {
+            Input::Char(c) => {
+                self.typed.push(c);
+                self.update(false)
+            }
+            Input::Backspace if !self.typed.is_empty() => {
+                self.typed.pop();
+                vec![EngineAction::Preedit(self.preedit())]
+            }
+            Input::Confirm if !self.typed.is_empty() => self.update(true),
+            Input::Cancel if !self.typed.is_empty() => {
+                self.typed.clear();
+                vec![EngineAction::Preedit(String::new())]
+            }
+            // Any other input ends the typed chars
+            input => {
+                let mut actions = self.update(true);
+                actions.push(EngineAction::Forward(input));
+                actions
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.typed.clear();
+    }
+}
+
+/// Parses the rules like ("sh" "ш") of a line. A ';' outside of a string starts a comment
+fn parse_rules(line: &str) -> Vec<(String, String)> {
+    let mut rules = Vec::new();
+    let mut strings = Vec::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => break,
+            '(' => strings.clear(),
+            '"' => match parse_string(&mut chars) {
+                Some(string) => strings.push(string),
+                None => break,
+            },
+            ')' => {
+                if let [input, output] = strings.as_slice() {
+                    rules.push((input.clone(), output.clone()));
+                }
+                strings.clear();
+            }
+            _ => (),
+        }
+    }
+    rules
+}
+
+/// Parses a string after its opening quote until the closing quote. A '\' escapes the next char
+fn parse_string(chars: &mut std::str::Chars) -> Option<String> {
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => string.push(chars.next()?),
+            c => string.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::type_inputs;
+
+    fn engine() -> TransliterationEngine {
+        let mut engine = TransliterationEngine::new();
+        for rule in
+            parse_rules(r#"("s" "с") ("h" "х") ("c" "ц") ("sh" "ш") ("shch" "щ") ; ("a" "б")"#)
+        {
+            engine.add_rule(rule.0, rule.1);
+        }
+        engine
+    }
+
+    fn chars(text: &str) -> Vec<Input> {
+        text.chars().map(Input::Char).collect()
+    }
+
+    #[test]
+    fn rules_are_parsed() {
+        assert_eq!(
+            parse_rules(r#"("\"" "«") ("a\\b" "c") ("x") ; ("y" "z")"#),
+            vec![
+                ("\"".to_string(), "«".to_string()),
+                ("a\\b".to_string(), "c".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn longest_rule_is_applied() {
+        let mut engine = engine();
+        assert_eq!(
+            type_inputs(&mut engine, chars("shch")),
+            ("щ".to_string(), String::new())
+        );
+        assert_eq!(
+            type_inputs(&mut engine, chars("sha")),
+            ("шa".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn shorter_rules_are_applied_if_a_longer_rule_does_not_match() {
+        let mut engine = engine();
+        assert_eq!(
+            type_inputs(&mut engine, chars("shc")),
+            (String::new(), "шц".to_string())
+        );
+        // The 'a' ends the possible 'shch', so 'sh' and 'c' are transliterated separately
+        assert_eq!(
+            type_inputs(&mut engine, chars("a")),
+            ("шцa".to_string(), String::new())
+        );
+        // A confirmation ends the possible 'shch' as well
+        assert_eq!(
+            type_inputs(
+                &mut engine,
+                vec![Input::Char('s'), Input::Char('h'), Input::Confirm]
+            ),
+            ("ш".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn backspace_removes_a_typed_char() {
+        let mut engine = engine();
+        let mut inputs = chars("shc");
+        inputs.push(Input::Backspace);
+        assert_eq!(
+            type_inputs(&mut engine, inputs),
+            (String::new(), "ш".to_string())
+        );
+        assert_eq!(
+            type_inputs(&mut engine, vec![Input::Cancel]),
+            (String::new(), String::new())
+        );
+    }
+}
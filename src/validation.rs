@@ -0,0 +1,248 @@
+use super::{Request, SubmitError, MAX_SURROUNDING_TEXT_LEN};
+
+/// Decides how requests are checked against the rules of the input-method-v2 protocol before they are sent
+///
+/// The wayland server silently ignores invalid requests or disconnects the input method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// Requests are sent without checking them (default)
+    #[default]
+    Off,
+    /// Invalid requests are not sent and fail with SubmitError::ProtocolViolation
+    Error,
+    /// Invalid requests trigger a debug assertion. In release builds they are sent anyway
+    DebugAssert,
+}
+
+impl ValidationMode {
+    /// Reports the violation according to the mode
+    pub(crate) fn report(self, violation: ProtocolViolation) -> Result<(), SubmitError> {
+        match self {
+            ValidationMode::Off => Ok(()),
+            ValidationMode::Error => Err(SubmitError::ProtocolViolation(violation)),
+            ValidationMode::DebugAssert => {
+                debug_assert!(false, "The request violates the protocol: {:?}", violation);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Violation of a rule of the input-method-v2 protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolViolation {
+    /// The serial of a commit has to be the number of 'done' events received
+    WrongSerial { expected: u32, actual: u32 },
+    /// The range to delete exceeds the surrounding text
+    DeleteOutOfRange {
+        before_length: usize,
+        after_length: usize,
+        cursor: usize,
+        text_length: usize,
+    },
+    /// The range to delete starts or ends within a char
+    DeleteSplitsChar,
+    /// The cursor of the preedit string is outside of the preedit string or within a char
+    PreeditCursorOutOfRange { cursor_begin: i32, cursor_end: i32 },
+    /// The text is too long for a wayland message
+    TextTooLong { length: usize },
+    /// The wayland server sent 'unavailable', so the input method must not send any requests anymore
+    Unavailable,
+}
+
+/// State of the input method the requests are checked against
+pub(crate) struct ValidationState<'a> {
+    pub surrounding_text: &'a str,
+    pub cursor: usize,
+    pub done_count: u32,
+    pub unavailable: bool,
+}
+
+impl ValidationState<'_> {
+    /// Checks the request against the rules of the protocol
+    pub(crate) fn check(&self, request: &Request) -> Result<(), ProtocolViolation> {
+        if self.unavailable {
+            return Err(ProtocolViolation::Unavailable);
+        }
+        match request {
+            Request::CommitString { text } => check_length(text),
+            Request::DeleteSurroundingText {
+                before_length,
+                after_length,
+            } => self.check_delete(*before_length, *after_length),
+            Request::SetPreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => {
+                check_length(text)?;
+                let is_valid = |offset: i32| {
+                    offset == -1 || (offset >= 0 && text.is_char_boundary(offset as usize))
+                };
+                match is_valid(*cursor_begin) && is_valid(*cursor_end) {
+                    true => Ok(()),
+                    false => Err(ProtocolViolation::PreeditCursorOutOfRange {
+                        cursor_begin: *cursor_begin,
+                        cursor_end: *cursor_end,
+                    }),
+                }
+            }
+            Request::Commit { serial } => match *serial == self.done_count {
+                true => Ok(()),
+                false => Err(ProtocolViolation::WrongSerial {
+                    expected: self.done_count,
+                    actual: *serial,
+                }),
+            },
+        }
+    }
+
+    /// Checks if the range to delete is within the surrounding text and on char boundaries
+    fn check_delete(
+        &self,
+        before_length: usize,
+        after_length: usize,
+    ) -> Result<(), ProtocolViolation> {
+        let text = self.surrounding_text;
+        if before_length > self.cursor || self.cursor + after_length > text.len() {
+            return Err(ProtocolViolation::DeleteOutOfRange {
+                before_length,
+                after_length,
+                cursor: self.cursor,
+                text_length: text.len(),
+            });
+        }
+        match text.is_char_boundary(self.cursor - before_length)
+            && text.is_char_boundary(self.cursor + after_length)
+        {
+            true => Ok(()),
+            false => Err(ProtocolViolation::DeleteSplitsChar),
+        }
+    }
+}
+
+/// Checks if the text fits into a wayland message
+fn check_length(text: &str) -> Result<(), ProtocolViolation> {
+    match text.len() > MAX_SURROUNDING_TEXT_LEN {
+        true => Err(ProtocolViolation::TextTooLong { length: text.len() }),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATE: ValidationState = ValidationState {
+        surrounding_text: "aöb",
+        cursor: 3,
+        done_count: 2,
+        unavailable: false,
+    };
+
+    fn preedit(text: &str, cursor_begin: i32, cursor_end: i32) -> Request {
+        Request::SetPreeditString {
+            text: text.to_string(),
+            cursor_begin,
+            cursor_end,
+        }
+    }
+
+    fn delete(before_length: usize, after_length: usize) -> Request {
+        Request::DeleteSurroundingText {
+            before_length,
+            after_length,
+        }
+    }
+
+    #[test]
+    fn valid_requests_pass() {
+        let requests = [
+            Request::CommitString {
+                text: "ö".to_string(),
+            },
+            delete(3, 1),
+            delete(0, 0),
+            preedit("aö", 3, 3),
+            preedit("aö", 1, 3),
+            preedit("aö", -1, -1),
+            Request::Commit { serial: 2 },
+        ];
+        for request in requests {
+            assert_eq!(STATE.check(&request), Ok(()), "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn violations_are_detected() {
+        let out_of_range = |before_length, after_length| ProtocolViolation::DeleteOutOfRange {
+            before_length,
+            after_length,
+            cursor: 3,
+            text_length: 4,
+        };
+        let cursor_out_of_range =
+            |cursor_begin, cursor_end| ProtocolViolation::PreeditCursorOutOfRange {
+                cursor_begin,
+                cursor_end,
+            };
+        let cases = [
+            (delete(4, 0), out_of_range(4, 0)),
+            (delete(0, 2), out_of_range(0, 2)),
+            (delete(1, 0), ProtocolViolation::DeleteSplitsChar),
+            (preedit("aö", 4, 4), cursor_out_of_range(4, 4)),
+            (preedit("aö", 2, 3), cursor_out_of_range(2, 3)),
+            (preedit("aö", 0, -2), cursor_out_of_range(0, -2)),
+            (
+                Request::Commit { serial: 1 },
+                ProtocolViolation::WrongSerial {
+                    expected: 2,
+                    actual: 1,
+                },
+            ),
+            (
+                Request::CommitString {
+                    text: "a".repeat(MAX_SURROUNDING_TEXT_LEN + 1),
+                },
+                ProtocolViolation::TextTooLong {
+                    length: MAX_SURROUNDING_TEXT_LEN + 1,
+                },
+            ),
+        ];
+        for (request, violation) in cases {
+            assert_eq!(STATE.check(&request), Err(violation), "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn no_requests_pass_when_unavailable() {
+        let state = ValidationState {
+            unavailable: true,
+            ..STATE
+        };
+        for request in [delete(0, 0), Request::Commit { serial: 2 }] {
+            assert_eq!(state.check(&request), Err(ProtocolViolation::Unavailable));
+        }
+    }
+
+    #[test]
+    fn violations_are_reported_by_mode() {
+        assert_eq!(
+            ValidationMode::Off.report(ProtocolViolation::Unavailable),
+            Ok(())
+        );
+        assert_eq!(
+            ValidationMode::Error.report(ProtocolViolation::Unavailable),
+            Err(SubmitError::ProtocolViolation(
+                ProtocolViolation::Unavailable
+            ))
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "The request violates the protocol: Unavailable")]
+    fn violations_fail_debug_assertions() {
+        let _ = ValidationMode::DebugAssert.report(ProtocolViolation::Unavailable);
+    }
+}
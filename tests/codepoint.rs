@@ -0,0 +1,63 @@
+mod common;
+
+use zwp_input_method_service::{CodePointEngine, Input, Pipeline, SubmitError};
+
+const TRIGGER: u32 = 1000;
+
+#[test]
+fn half_typed_code_point_is_canceled_on_deactivation() {
+    let im_service = common::detached();
+    let mut pipeline = Pipeline::new().with(CodePointEngine::new(TRIGGER));
+    common::activate(&im_service, "", 0);
+    for input in [Input::Key(TRIGGER), Input::Char('4'), Input::Char('1')] {
+        pipeline.process(&im_service, input).unwrap();
+    }
+    assert_eq!(pipeline.preedit(), "u41");
+
+    common::deactivate(&im_service);
+    common::activate(&im_service, "", 0);
+    assert!(pipeline
+        .process(&im_service, Input::Char(' '))
+        .unwrap()
+        .is_empty());
+    assert_eq!(pipeline.preedit(), "");
+    assert_eq!(im_service.get_preedit().text, "");
+    assert_eq!(common::text(&im_service), (" ".to_string(), String::new()));
+}
+
+#[test]
+fn input_while_deactivated_cancels_the_code_point() {
+    let im_service = common::detached();
+    let mut pipeline = Pipeline::new().with(CodePointEngine::new(TRIGGER));
+    common::activate(&im_service, "", 0);
+    for input in [Input::Key(TRIGGER), Input::Char('4'), Input::Char('1')] {
+        pipeline.process(&im_service, input).unwrap();
+    }
+
+    common::deactivate(&im_service);
+    assert!(matches!(
+        pipeline.process(&im_service, Input::Confirm),
+        Err(SubmitError::NotActive)
+    ));
+    assert_eq!(pipeline.preedit(), "");
+    common::activate(&im_service, "", 0);
+    pipeline.process(&im_service, Input::Char('1')).unwrap();
+    assert_eq!(common::text(&im_service), ("1".to_string(), String::new()));
+}
+
+#[test]
+fn code_point_is_committed_on_confirm() {
+    let im_service = common::detached();
+    let mut pipeline = Pipeline::new().with(CodePointEngine::new(TRIGGER));
+    common::activate(&im_service, "", 0);
+    for input in [
+        Input::Key(TRIGGER),
+        Input::Char('e'),
+        Input::Char('9'),
+        Input::Confirm,
+    ] {
+        pipeline.process(&im_service, input).unwrap();
+    }
+    assert_eq!(pipeline.preedit(), "");
+    assert_eq!(common::text(&im_service), ("é".to_string(), String::new()));
+}
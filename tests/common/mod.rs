@@ -0,0 +1,105 @@
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ChangeCause, ContentHint, ContentPurpose,
+};
+use zwp_input_method_service::{
+    Divergence, Event, HintPurpose, IMService, IMVisibility, ReceiveSurroundingText, TextDelta,
+};
+
+/// Connector that remembers the calls of the IMService
+#[derive(Clone, Debug, Default)]
+pub struct Connector {
+    pub calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl IMVisibility for Connector {
+    fn activate_im(&self) {
+        self.calls.lock().unwrap().push("activate".to_string());
+    }
+
+    fn deactivate_im(&self) {
+        self.calls.lock().unwrap().push("deactivate".to_string());
+    }
+
+    fn show_candidates(&self, candidates: &[String]) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("candidates {}", candidates.join(",")));
+    }
+}
+
+impl HintPurpose for Connector {
+    fn set_hint_purpose(&self, _content_hint: ContentHint, _content_purpose: ContentPurpose) {}
+}
+
+impl ReceiveSurroundingText for Connector {
+    fn text_changed(&self, string_left_of_cursor: String, string_right_of_cursor: String) {
+        self.calls.lock().unwrap().push(format!(
+            "text {}|{}",
+            string_left_of_cursor, string_right_of_cursor
+        ));
+    }
+
+    fn text_delta(&self, delta: TextDelta) {
+        self.calls.lock().unwrap().push(format!(
+            "delta {:?} {} echo={}",
+            delta.removed, delta.inserted, delta.echo
+        ));
+    }
+
+    fn text_diverged(&self, divergence: Divergence) {
+        self.calls.lock().unwrap().push(format!(
+            "diverged {} {}",
+            divergence.predicted_text, divergence.confirmed_text
+        ));
+    }
+}
+
+impl Connector {
+    /// Returns the calls and forgets them
+    pub fn take_calls(&self) -> Vec<String> {
+        std::mem::take(&mut self.calls.lock().unwrap())
+    }
+}
+
+/// Returns a detached IMService
+pub fn detached() -> IMService<Connector, Connector> {
+    IMService::new_detached(Connector::default(), Connector::default())
+}
+
+/// Sends the events of the wayland server to activate a text input with the text and the cursor at 'cursor'
+pub fn activate(im_service: &IMService<Connector, Connector>, text: &str, cursor: usize) {
+    im_service.handle_event(Event::Activate);
+    surrounding_text(im_service, text, cursor, ChangeCause::Other);
+}
+
+/// Sends the events of the wayland server for a change of the surrounding text
+pub fn surrounding_text(
+    im_service: &IMService<Connector, Connector>,
+    text: &str,
+    cursor: usize,
+    cause: ChangeCause,
+) {
+    im_service.handle_event(Event::SurroundingText {
+        text: text.to_string(),
+        cursor,
+        anchor: cursor,
+    });
+    im_service.handle_event(Event::TextChangeCause { cause });
+    im_service.handle_event(Event::Done);
+}
+
+/// Sends the events of the wayland server to deactivate the text input
+pub fn deactivate(im_service: &IMService<Connector, Connector>) {
+    im_service.handle_event(Event::Deactivate);
+    im_service.handle_event(Event::Done);
+}
+
+/// Returns the current text left and right of the cursor
+pub fn text(im_service: &IMService<Connector, Connector>) -> (String, String) {
+    use zwp_input_method_service::InputMethod;
+    im_service.get_surrounding_text()
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ChangeCause;
+use zwp_input_method_service::{Event, TextContext};
+
+#[test]
+fn cursors_outside_the_surrounding_text_do_not_panic() {
+    let im_service = common::detached();
+    im_service.set_auto_capitalization(true);
+    im_service.handle_event(Event::Activate);
+    for (text, cursor) in [("ab", 5), ("äb", 1)] {
+        im_service.handle_event(Event::SurroundingText {
+            text: text.to_string(),
+            cursor,
+            anchor: cursor,
+        });
+        im_service.handle_event(Event::Done);
+        assert_eq!(im_service.text_context(2), TextContext::new("", 0, 2));
+        assert_eq!(common::text(&im_service), (String::new(), String::new()));
+    }
+    // The service is still usable
+    common::surrounding_text(&im_service, "Hi. ", 4, ChangeCause::Other);
+    assert!(im_service.text_context(0).at_sentence_start);
+}
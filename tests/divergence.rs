@@ -0,0 +1,90 @@
+mod common;
+
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ChangeCause;
+use zwp_input_method_service::{IMService, InputMethod, MAX_SURROUNDING_TEXT_LEN};
+
+fn text(before: &str) -> (String, String) {
+    (before.to_string(), String::new())
+}
+
+/// Returns a detached IMService with an active text input and its content connector
+fn activated(
+    text: &str,
+) -> (
+    IMService<common::Connector, common::Connector>,
+    common::Connector,
+) {
+    let content_connector = common::Connector::default();
+    let im_service =
+        IMService::new_detached(common::Connector::default(), content_connector.clone());
+    common::activate(&im_service, text, text.len());
+    content_connector.take_calls();
+    (im_service, content_connector)
+}
+
+fn commit(im_service: &IMService<common::Connector, common::Connector>, text: &str) {
+    im_service.commit_string(text.to_string()).unwrap();
+    im_service.commit().unwrap();
+}
+
+#[test]
+fn echoes_of_earlier_commits_keep_later_commits() {
+    let (im_service, content_connector) = activated("");
+    commit(&im_service, "a");
+    commit(&im_service, "b");
+    content_connector.take_calls();
+    common::surrounding_text(&im_service, "a", 1, ChangeCause::InputMethod);
+    assert_eq!(common::text(&im_service), text("ab"));
+    common::surrounding_text(&im_service, "ab", 2, ChangeCause::InputMethod);
+    assert_eq!(common::text(&im_service), text("ab"));
+    assert_eq!(content_connector.take_calls(), Vec::<String>::new());
+}
+
+#[test]
+fn skipped_echoes_confirm_earlier_commits() {
+    let (im_service, content_connector) = activated("");
+    commit(&im_service, "a");
+    commit(&im_service, "b");
+    content_connector.take_calls();
+    // The wayland server only sends the text after both commits
+    common::surrounding_text(&im_service, "ab", 2, ChangeCause::InputMethod);
+    assert_eq!(common::text(&im_service), text("ab"));
+    assert_eq!(content_connector.take_calls(), Vec::<String>::new());
+}
+
+#[test]
+fn rejected_commits_diverge() {
+    let (im_service, content_connector) = activated("12");
+    commit(&im_service, "x");
+    commit(&im_service, "3");
+    content_connector.take_calls();
+    common::surrounding_text(&im_service, "12", 2, ChangeCause::InputMethod);
+    assert_eq!(common::text(&im_service), text("12"));
+    assert_eq!(
+        content_connector.take_calls(),
+        vec!["diverged 12x 12", "delta 2..4  echo=false", "text 12|"]
+    );
+    // The later commit is applied to the confirmed text and is not compared to the outdated prediction
+    common::surrounding_text(&im_service, "123", 3, ChangeCause::InputMethod);
+    assert_eq!(common::text(&im_service), text("123"));
+    assert_eq!(
+        content_connector.take_calls(),
+        vec!["delta 2..2 3 echo=false", "text 123|"]
+    );
+}
+
+#[test]
+fn truncated_texts_do_not_diverge() {
+    let long_text = "a".repeat(MAX_SURROUNDING_TEXT_LEN - 2);
+    let (im_service, content_connector) = activated(&long_text);
+    commit(&im_service, "b");
+    content_connector.take_calls();
+    // The wayland server sends a window of the text around the cursor
+    let window = format!("{}b", &long_text[1..]);
+    common::surrounding_text(&im_service, &window, window.len(), ChangeCause::InputMethod);
+    assert!(!content_connector
+        .take_calls()
+        .iter()
+        .any(|call| call.starts_with("diverged")));
+    assert_eq!(common::text(&im_service), text(&window));
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ChangeCause;
+use zwp_input_method_service::{EchoMode, IMService, InputMethod};
+
+/// Commits "c" after "ab" with the echo mode, sends the echo of the wayland server
+/// and returns the calls of the content connector for the commit and for the echo
+fn commit_and_echo(echo_mode: EchoMode) -> (Vec<String>, Vec<String>) {
+    let content_connector = common::Connector::default();
+    let im_service =
+        IMService::new_detached(common::Connector::default(), content_connector.clone());
+    im_service.set_echo_mode(echo_mode);
+    common::activate(&im_service, "ab", 2);
+    content_connector.take_calls();
+    im_service.commit_string("c".to_string()).unwrap();
+    im_service.commit().unwrap();
+    let commit_calls = content_connector.take_calls();
+    common::surrounding_text(&im_service, "abc", 3, ChangeCause::InputMethod);
+    (commit_calls, content_connector.take_calls())
+}
+
+#[test]
+fn echoes_are_reported_in_notify_mode() {
+    let (commit_calls, echo_calls) = commit_and_echo(EchoMode::Notify);
+    assert_eq!(commit_calls, vec!["delta 2..2 c echo=false", "text abc|"]);
+    assert_eq!(echo_calls, Vec::<String>::new());
+}
+
+#[test]
+fn echoes_are_tagged_in_tag_mode() {
+    let (commit_calls, echo_calls) = commit_and_echo(EchoMode::Tag);
+    assert_eq!(commit_calls, vec!["delta 2..2 c echo=true", "text abc|"]);
+    assert_eq!(echo_calls, Vec::<String>::new());
+}
+
+#[test]
+fn echoes_are_not_reported_in_suppress_mode() {
+    let (commit_calls, echo_calls) = commit_and_echo(EchoMode::Suppress);
+    assert_eq!(commit_calls, Vec::<String>::new());
+    assert_eq!(echo_calls, Vec::<String>::new());
+}
+
+#[test]
+fn changes_of_the_application_are_never_echoes() {
+    for echo_mode in [EchoMode::Notify, EchoMode::Tag, EchoMode::Suppress] {
+        let content_connector = common::Connector::default();
+        let im_service =
+            IMService::new_detached(common::Connector::default(), content_connector.clone());
+        im_service.set_echo_mode(echo_mode);
+        common::activate(&im_service, "ab", 2);
+        content_connector.take_calls();
+        common::surrounding_text(&im_service, "abd", 3, ChangeCause::Other);
+        assert_eq!(
+            content_connector.take_calls(),
+            vec!["delta 2..2 d echo=false", "text abd|"]
+        );
+    }
+}
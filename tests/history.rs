@@ -0,0 +1,105 @@
+mod common;
+
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ChangeCause;
+use zwp_input_method_service::{Event, IMService, Transaction};
+
+/// Submits a transaction and sends the surrounding text the wayland server echoes back
+fn submit(im_service: &IMService<common::Connector, common::Connector>, transaction: Transaction) {
+    im_service.submit(transaction).unwrap();
+    echo(im_service);
+}
+
+/// Sends the current surrounding text back like the wayland server does after a commit
+fn echo(im_service: &IMService<common::Connector, common::Connector>) {
+    let (before, after) = common::text(im_service);
+    common::surrounding_text(
+        im_service,
+        &(before.clone() + &after),
+        before.len(),
+        ChangeCause::InputMethod,
+    );
+}
+
+fn text(before: &str) -> (String, String) {
+    (before.to_string(), String::new())
+}
+
+#[test]
+fn edits_are_undone_and_redone() {
+    let im_service = common::detached();
+    common::activate(&im_service, "ab", 2);
+    submit(
+        &im_service,
+        Transaction::new().commit_string("c".to_string()),
+    );
+    submit(
+        &im_service,
+        Transaction::new()
+            .delete_surrounding_text(1, 0)
+            .commit_string("é".to_string()),
+    );
+    assert_eq!(common::text(&im_service), text("abé"));
+
+    assert_eq!(im_service.undo(), Ok(true));
+    echo(&im_service);
+    assert_eq!(common::text(&im_service), text("abc"));
+    assert_eq!(im_service.undo(), Ok(true));
+    echo(&im_service);
+    assert_eq!(common::text(&im_service), text("ab"));
+    assert_eq!(im_service.undo(), Ok(false));
+
+    assert_eq!(im_service.redo(), Ok(true));
+    echo(&im_service);
+    assert_eq!(common::text(&im_service), text("abc"));
+    assert_eq!(im_service.redo(), Ok(true));
+    echo(&im_service);
+    assert_eq!(common::text(&im_service), text("abé"));
+    assert_eq!(im_service.redo(), Ok(false));
+}
+
+#[test]
+fn new_edits_can_not_be_redone() {
+    let im_service = common::detached();
+    common::activate(&im_service, "", 0);
+    submit(
+        &im_service,
+        Transaction::new().commit_string("a".to_string()),
+    );
+    assert_eq!(im_service.undo(), Ok(true));
+    echo(&im_service);
+    submit(
+        &im_service,
+        Transaction::new().commit_string("b".to_string()),
+    );
+    assert_eq!(im_service.redo(), Ok(false));
+    assert_eq!(common::text(&im_service), text("b"));
+}
+
+#[test]
+fn history_is_cleared_when_the_application_changes_the_text() {
+    let im_service = common::detached();
+    common::activate(&im_service, "ab", 2);
+    submit(
+        &im_service,
+        Transaction::new().commit_string("c".to_string()),
+    );
+    common::surrounding_text(&im_service, "abc", 3, ChangeCause::Other);
+    assert_eq!(im_service.undo(), Ok(false));
+    assert_eq!(common::text(&im_service), text("abc"));
+}
+
+#[test]
+fn history_is_cleared_when_a_text_input_is_activated() {
+    let im_service = common::detached();
+    common::activate(&im_service, "ab", 2);
+    submit(
+        &im_service,
+        Transaction::new().commit_string("c".to_string()),
+    );
+    common::deactivate(&im_service);
+    // The text and the cause are unchanged, only the activation clears the history
+    im_service.handle_event(Event::Activate);
+    common::surrounding_text(&im_service, "abc", 3, ChangeCause::InputMethod);
+    assert_eq!(im_service.undo(), Ok(false));
+    assert_eq!(common::text(&im_service), text("abc"));
+}
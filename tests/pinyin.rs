@@ -0,0 +1,66 @@
+mod common;
+
+use zwp_input_method_service::{IMService, Input, PinyinEngine, Pipeline};
+
+fn pipeline() -> Pipeline {
+    let mut engine = PinyinEngine::new();
+    engine.add_phrase("你好".to_string(), &["ni", "hao"], 100);
+    engine.add_phrase("你".to_string(), &["ni"], 50);
+    Pipeline::new().with(engine)
+}
+
+#[test]
+fn candidates_are_passed_to_the_ui_connector() {
+    let ui_connector = common::Connector::default();
+    let im_service = IMService::new_detached(ui_connector.clone(), common::Connector::default());
+    let mut pipeline = pipeline();
+    common::activate(&im_service, "", 0);
+    for c in "nihao".chars() {
+        pipeline.process(&im_service, Input::Char(c)).unwrap();
+    }
+    pipeline.process(&im_service, Input::Char(' ')).unwrap();
+    assert_eq!(
+        common::text(&im_service),
+        ("你好".to_string(), String::new())
+    );
+    let calls: Vec<String> = ui_connector
+        .calls
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|call| call.starts_with("candidates"))
+        .cloned()
+        .collect();
+    assert_eq!(
+        calls,
+        vec!["candidates 你", "candidates 你好,你", "candidates "]
+    );
+}
+
+#[test]
+fn candidates_are_hidden_after_deactivation() {
+    let ui_connector = common::Connector::default();
+    let im_service = IMService::new_detached(ui_connector.clone(), common::Connector::default());
+    let mut pipeline = pipeline();
+    common::activate(&im_service, "", 0);
+    pipeline.process(&im_service, Input::Char('n')).unwrap();
+    common::deactivate(&im_service);
+    assert!(pipeline.process(&im_service, Input::Char('i')).is_err());
+    let calls = ui_connector.calls.lock().unwrap();
+    assert_eq!(calls.last().unwrap(), "candidates ");
+}
+
+#[test]
+fn space_without_candidates_commits_the_typed_letters() {
+    let im_service = common::detached();
+    let mut pipeline = pipeline();
+    common::activate(&im_service, "", 0);
+    for c in "xq ".chars() {
+        pipeline.process(&im_service, Input::Char(c)).unwrap();
+    }
+    assert_eq!(pipeline.preedit(), "");
+    assert_eq!(
+        common::text(&im_service),
+        ("xq ".to_string(), String::new())
+    );
+}
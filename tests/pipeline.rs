@@ -0,0 +1,63 @@
+mod common;
+
+use zwp_input_method_service::{EngineAction, EngineContext, Event, Input, InputEngine, Pipeline};
+
+/// Engine that keeps all typed chars as its preedit string until Input::Confirm commits them
+#[derive(Default)]
+struct Buffer {
+    typed: String,
+}
+
+impl InputEngine for Buffer {
+    fn process(&mut self, input: Input, _context: &EngineContext) -> Vec<EngineAction> {
+        match input {
+            Input::Char(c) => {
+                self.typed.push(c);
+                vec![EngineAction::Preedit(self.typed.clone())]
+            }
+            Input::Confirm => vec![
+                EngineAction::Preedit(String::new()),
+                EngineAction::Commit(std::mem::take(&mut self.typed)),
+            ],
+            input => vec![EngineAction::Forward(input)],
+        }
+    }
+
+    fn reset(&mut self) {
+        self.typed.clear();
+    }
+}
+
+#[test]
+fn engines_are_reset_when_another_text_input_is_activated() {
+    let im_service = common::detached();
+    let mut pipeline = Pipeline::new().with(Buffer::default());
+    common::activate(&im_service, "", 0);
+    pipeline.process(&im_service, Input::Char('a')).unwrap();
+    assert_eq!(pipeline.preedit(), "a");
+
+    // No input is processed while the input method is inactive
+    common::deactivate(&im_service);
+    common::activate(&im_service, "", 0);
+    pipeline.process(&im_service, Input::Char('b')).unwrap();
+    assert_eq!(pipeline.preedit(), "b");
+    pipeline.process(&im_service, Input::Confirm).unwrap();
+    assert_eq!(im_service.get_preedit().text, "");
+    assert_eq!(common::text(&im_service), ("b".to_string(), String::new()));
+}
+
+#[test]
+fn inputs_between_surrounding_text_and_done_use_the_current_text() {
+    let im_service = common::detached();
+    let mut pipeline = Pipeline::new();
+    common::activate(&im_service, "abc", 3);
+    // The cursor of the current state would split the 'ö' of the pending text
+    im_service.handle_event(Event::SurroundingText {
+        text: "aöc".to_string(),
+        cursor: 4,
+        anchor: 4,
+    });
+    pipeline.process(&im_service, Input::Backspace).unwrap();
+    // The wayland server applies the deletion to the text it sent last
+    assert_eq!(common::text(&im_service), ("aö".to_string(), String::new()));
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "record")]
+
+mod common;
+
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
+use zwp_input_method_service::{Event, Record, Replayer, Transaction};
+
+fn recorded_texts(path: &std::path::Path) -> Vec<String> {
+    Replayer::open(path)
+        .unwrap()
+        .records()
+        .iter()
+        .filter_map(|record| match record {
+            Record::Event(Event::SurroundingText { text, .. }) => Some(text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn surrounding_text_before_the_content_type_of_a_password_field_is_masked() {
+    let path = std::env::temp_dir().join("zwp_im_recording_password.jsonl");
+    let im_service = common::detached();
+    im_service.start_recording(&path).unwrap();
+    im_service.handle_event(Event::Activate);
+    im_service.handle_event(Event::SurroundingText {
+        text: "secret".to_string(),
+        cursor: 6,
+        anchor: 6,
+    });
+    im_service.handle_event(Event::ContentType {
+        hint: ContentHint::None,
+        purpose: ContentPurpose::Password,
+    });
+    im_service.handle_event(Event::Done);
+    im_service.stop_recording();
+
+    assert_eq!(recorded_texts(&path), vec!["******".to_string()]);
+}
+
+#[test]
+fn texts_of_normal_fields_are_recorded_in_order() {
+    let path = std::env::temp_dir().join("zwp_im_recording_normal.jsonl");
+    let im_service = common::detached();
+    im_service.start_recording(&path).unwrap();
+    common::activate(&im_service, "hello", 5);
+    im_service
+        .submit(Transaction::new().commit_string(" world".to_string()))
+        .unwrap();
+    im_service.stop_recording();
+
+    let replayer = Replayer::open(&path).unwrap();
+    assert_eq!(recorded_texts(&path), vec!["hello".to_string()]);
+    assert!(matches!(
+        replayer.records().last(),
+        Some(Record::Request(zwp_input_method_service::Request::Commit {
+            serial: 1
+        }))
+    ));
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ChangeCause, ContentHint, ContentPurpose,
+};
+use zwp_input_method_service::{Event, IMService, ReturnKeyAction, SubmitError, KEY_ENTER};
+
+/// Activates a text input with the content type and the text "ab"
+fn activate(
+    hint: ContentHint,
+    purpose: ContentPurpose,
+) -> IMService<common::Connector, common::Connector> {
+    let im_service = common::detached();
+    im_service.handle_event(Event::Activate);
+    im_service.handle_event(Event::ContentType { hint, purpose });
+    common::surrounding_text(&im_service, "ab", 2, ChangeCause::Other);
+    im_service
+}
+
+#[test]
+fn multiline_inputs_get_a_newline() {
+    let im_service = activate(ContentHint::Multiline, ContentPurpose::Normal);
+    let mut sent_keys = Vec::new();
+    let action = im_service.press_return(|key| sent_keys.push(key));
+    assert_eq!(action, Ok(ReturnKeyAction::Newline));
+    assert_eq!(sent_keys, Vec::<u32>::new());
+    assert_eq!(
+        common::text(&im_service),
+        ("ab\n".to_string(), String::new())
+    );
+}
+
+#[test]
+fn single_line_inputs_get_the_enter_key() {
+    let cases = [
+        (ContentPurpose::Normal, ReturnKeyAction::Enter),
+        (ContentPurpose::Url, ReturnKeyAction::Go),
+        (ContentPurpose::Email, ReturnKeyAction::Next),
+        (ContentPurpose::Phone, ReturnKeyAction::Next),
+        (ContentPurpose::Number, ReturnKeyAction::Done),
+        (ContentPurpose::Password, ReturnKeyAction::Done),
+    ];
+    for (purpose, expected) in cases {
+        let im_service = activate(ContentHint::None, purpose);
+        let mut sent_keys = Vec::new();
+        let action = im_service.press_return(|key| sent_keys.push(key));
+        assert_eq!(action, Ok(expected), "{:?}", purpose);
+        assert_eq!(sent_keys, vec![KEY_ENTER]);
+        assert_eq!(common::text(&im_service), ("ab".to_string(), String::new()));
+    }
+}
+
+#[test]
+fn return_is_rejected_without_a_text_input() {
+    let im_service = common::detached();
+    let mut sent_keys = Vec::new();
+    let action = im_service.press_return(|key| sent_keys.push(key));
+    assert_eq!(action, Err(SubmitError::NotActive));
+    assert_eq!(sent_keys, Vec::<u32>::new());
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::ChangeCause;
+use zwp_input_method_service::InputMethod;
+
+#[derive(Default)]
+struct State {
+    // Names of the spans, the id of a span is its index + 1
+    spans: Vec<&'static str>,
+    stack: Vec<u64>,
+    // Names of the events and requests with the spans they were emitted in
+    events: Vec<(String, String)>,
+}
+
+/// Subscriber that remembers the spans each event was emitted in
+#[derive(Clone, Default)]
+struct Collector {
+    state: Arc<Mutex<State>>,
+}
+
+/// Finds the name of an event or a request
+struct Name(String);
+
+impl Visit for Name {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "event" || field.name() == "request" {
+            self.0 = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut state = self.state.lock().unwrap();
+        state.spans.push(attributes.metadata().name());
+        Id::from_u64(state.spans.len() as u64)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut name = Name(String::new());
+        event.record(&mut name);
+        let mut state = self.state.lock().unwrap();
+        // Spans that are entered again are only counted once
+        let mut entered: Vec<u64> = Vec::new();
+        for id in &state.stack {
+            if !entered.contains(id) {
+                entered.push(*id);
+            }
+        }
+        let spans = entered
+            .iter()
+            .map(|id| format!("{}{}", state.spans[*id as usize - 1], id))
+            .collect::<Vec<_>>()
+            .join("/");
+        state.events.push((name.0, spans));
+    }
+
+    fn enter(&self, span: &Id) {
+        self.state.lock().unwrap().stack.push(span.into_u64());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(index) = state.stack.iter().rposition(|id| *id == span.into_u64()) {
+            state.stack.remove(index);
+        }
+    }
+}
+
+#[test]
+fn events_and_requests_are_grouped_by_cycle() {
+    let collector = Collector::default();
+    tracing::subscriber::with_default(collector.clone(), || {
+        let im_service = common::detached();
+        common::activate(&im_service, "", 0);
+        im_service.commit_string("a".to_string()).unwrap();
+        im_service.commit().unwrap();
+        common::surrounding_text(&im_service, "a", 1, ChangeCause::InputMethod);
+    });
+    let events = std::mem::take(&mut collector.state.lock().unwrap().events);
+    let expected = [
+        ("activate", "cycle1"),
+        ("surrounding_text", "cycle1"),
+        ("text_change_cause", "cycle1"),
+        ("done", "cycle1"),
+        // The requests after a 'done' event open the next cycle
+        ("commit_string", "cycle2"),
+        ("commit", "cycle2/commit3"),
+        ("surrounding_text", "cycle2"),
+        ("text_change_cause", "cycle2"),
+        ("done", "cycle2"),
+    ];
+    let events: Vec<(&str, &str)> = events
+        .iter()
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, spans)| (name.as_str(), spans.as_str()))
+        .collect();
+    assert_eq!(events, expected);
+}
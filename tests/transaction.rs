@@ -0,0 +1,57 @@
+mod common;
+
+use zwp_input_method_service::{Event, InputMethod, SubmitError, Transaction};
+
+#[test]
+fn submit_keeps_requests_sent_before_without_a_commit() {
+    let im_service = common::detached();
+    common::activate(&im_service, "ab", 2);
+    im_service.commit_string("x".to_string()).unwrap();
+    im_service
+        .submit(Transaction::new().delete_surrounding_text(1, 0))
+        .unwrap();
+    assert_eq!(common::text(&im_service), ("ax".to_string(), String::new()));
+}
+
+#[test]
+fn submit_replaces_requests_of_the_same_kind() {
+    let im_service = common::detached();
+    common::activate(&im_service, "ab", 2);
+    im_service.commit_string("x".to_string()).unwrap();
+    im_service
+        .submit(Transaction::new().commit_string("y".to_string()))
+        .unwrap();
+    assert_eq!(
+        common::text(&im_service),
+        ("aby".to_string(), String::new())
+    );
+}
+
+#[test]
+fn invalid_transactions_are_not_sent() {
+    let im_service = common::detached();
+    common::activate(&im_service, "ab", 2);
+    assert_eq!(
+        im_service.submit(Transaction::new().delete_surrounding_text(3, 0)),
+        Err(SubmitError::InvalidDeleteRange)
+    );
+    assert_eq!(common::text(&im_service), ("ab".to_string(), String::new()));
+}
+
+#[test]
+fn deletes_with_a_cursor_outside_the_text_are_rejected() {
+    let im_service = common::detached();
+    im_service.handle_event(Event::Activate);
+    for (text, cursor) in [("ab", 5), ("äb", 1)] {
+        im_service.handle_event(Event::SurroundingText {
+            text: text.to_string(),
+            cursor,
+            anchor: cursor,
+        });
+        im_service.handle_event(Event::Done);
+        assert_eq!(
+            im_service.delete_surrounding_text(1, 0),
+            Err(SubmitError::InvalidDeleteRange)
+        );
+    }
+}